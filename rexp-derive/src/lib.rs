@@ -0,0 +1,343 @@
+/// `#[derive(ToSexp, FromSexp)]` for the traits in `rexp::expr`.
+///
+/// - structs with named fields are keyword lists, `(:name "rexp" :port 8080)`
+/// - tuple structs are lists, and a tuple struct with one field is just that field
+/// - unit structs are `()`
+/// - enum variants are their name, or a list headed by it, like `(move 1 2)` or
+///   `(resize :width 3)`
+///
+/// The `#[sexp(...)]` attribute changes that:
+///
+/// - `rename = "name"` on a field or variant writes it as `name`
+/// - `positional` on a named field reads and writes it without its keyword,
+///   before the keyword fields; on a struct or variant it does so for every field
+/// - `default` on a field uses `Default::default()` when it's left out, and
+///   `default = "path"` calls `path()` instead
+///
+/// The field of a one field tuple struct can't have any of them.
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Fields, Ident, LitStr, Path};
+
+#[proc_macro_derive(ToSexp, attributes(sexp))]
+pub fn derive_to_sexp(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    to_sexp(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+#[proc_macro_derive(FromSexp, attributes(sexp))]
+pub fn derive_from_sexp(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_sexp(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+
+
+// The shape of a type
+
+enum Shape {
+    Struct(Form),
+    /// A tuple struct with one field.
+    Newtype,
+    Enum(Vec<Variant>),
+}
+
+struct Variant {
+    ident: Ident,
+    name: String,
+    form: Form,
+}
+
+/// The fields of a struct or variant.
+struct Form {
+    kind: Kind,
+    fields: Vec<Field>,
+}
+
+enum Kind {
+    Named,
+    Tuple,
+    Unit,
+}
+
+struct Field {
+    ident: Option<Ident>,
+    /// What it's called in the source, and in errors.
+    name: String,
+    positional: bool,
+    default: Option<Fallback>,
+    binding: Ident,
+}
+
+enum Fallback {
+    Trait,
+    Path(Path),
+}
+
+#[derive(Default)]
+struct Attrs {
+    rename: Option<String>,
+    positional: bool,
+    default: Option<Fallback>,
+}
+
+impl Attrs {
+    /// The `#[sexp(...)]` attributes, with anything but `allowed` an error.
+    fn parse(attrs: &[Attribute], allowed: &[&str]) -> syn::Result<Attrs> {
+        let mut parsed = Attrs::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("sexp")) {
+            attr.parse_nested_meta(|meta| {
+                let known = ["rename", "positional", "default"].iter().find(|name| meta.path.is_ident(name));
+                match known {
+                    Some(name) if allowed.contains(name) => {}
+                    Some(name) => return Err(meta.error(format!("`{}` can't be used here", name))),
+                    None => return Err(meta.error("expected `rename`, `positional` or `default`")),
+                }
+                if meta.path.is_ident("rename") {
+                    parsed.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("positional") {
+                    parsed.positional = true;
+                } else if meta.input.peek(syn::Token![=]) {
+                    parsed.default = Some(Fallback::Path(meta.value()?.parse::<LitStr>()?.parse()?));
+                } else {
+                    parsed.default = Some(Fallback::Trait);
+                }
+                Ok(())
+            })?;
+        }
+        Ok(parsed)
+    }
+}
+
+impl Shape {
+    fn of(input: &DeriveInput) -> syn::Result<Shape> {
+        match &input.data {
+            Data::Struct(data) => {
+                let attrs = Attrs::parse(&input.attrs, &["positional"])?;
+                match &data.fields {
+                    Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                        // The field is the whole sexp, so there's no name to
+                        // change and nothing to leave out
+                        Attrs::parse(&fields.unnamed[0].attrs, &[])?;
+                        Ok(Shape::Newtype)
+                    }
+                    fields => Ok(Shape::Struct(Form::of(fields, attrs.positional)?)),
+                }
+            }
+            Data::Enum(data) => {
+                Attrs::parse(&input.attrs, &[])?;
+                let variants = data.variants.iter().map(|variant| {
+                    let attrs = Attrs::parse(&variant.attrs, &["rename", "positional"])?;
+                    Ok(Variant {
+                        ident: variant.ident.clone(),
+                        name: attrs.rename.unwrap_or_else(|| variant.ident.to_string()),
+                        form: Form::of(&variant.fields, attrs.positional)?,
+                    })
+                });
+                variants.collect::<syn::Result<_>>().map(Shape::Enum)
+            }
+            Data::Union(_) => Err(Error::new_spanned(input, "unions can't be converted to sexps")),
+        }
+    }
+}
+
+impl Form {
+    fn of(fields: &Fields, positional: bool) -> syn::Result<Form> {
+        let kind = match fields {
+            Fields::Named(_) => Kind::Named,
+            Fields::Unnamed(_) => Kind::Tuple,
+            Fields::Unit => Kind::Unit,
+        };
+        let fields = fields.iter().enumerate().map(|(n, field)| {
+            let attrs = Attrs::parse(&field.attrs, &["rename", "positional", "default"])?;
+            let name = match (attrs.rename, &field.ident) {
+                (Some(name), _) => name,
+                (None, Some(ident)) => ident.to_string(),
+                (None, None) => n.to_string(),
+            };
+            Ok(Field {
+                ident: field.ident.clone(),
+                name,
+                positional: positional || attrs.positional || field.ident.is_none(),
+                default: attrs.default,
+                binding: format_ident!("field_{}", n),
+            })
+        });
+        Ok(Form { kind, fields: fields.collect::<syn::Result<_>>()? })
+    }
+
+    /// `path { a: field_0, .. }`, `path(field_0, ..)` or `path`, as either a
+    /// pattern or an expression.
+    fn construct(&self, path: TokenStream) -> TokenStream {
+        let bindings = self.fields.iter().map(|field| &field.binding);
+        match self.kind {
+            Kind::Named => {
+                let idents = self.fields.iter().map(|field| &field.ident);
+                quote!(#path { #(#idents: #bindings),* })
+            }
+            Kind::Tuple => quote!(#path(#(#bindings),*)),
+            Kind::Unit => path,
+        }
+    }
+
+    /// The list items for the bound fields, after `head`.
+    fn write(&self, head: Option<TokenStream>) -> TokenStream {
+        let positional = self.fields.iter().filter(|field| field.positional).map(|field| {
+            let binding = &field.binding;
+            quote!(::rexp::expr::ToSexp::to_sexp(#binding))
+        });
+        let keywords = self.fields.iter().filter(|field| !field.positional).map(|field| {
+            let (key, binding) = (symbol(&format!(":{}", field.name)), &field.binding);
+            quote!(#key, ::rexp::expr::ToSexp::to_sexp(#binding))
+        });
+        let items = head.into_iter().chain(positional).chain(keywords);
+        quote!(::rexp::expr::Sexp::List(::std::vec![#(#items),*]))
+    }
+
+    /// Binds each field read from `Form::#make(#input, ..)`.
+    fn read(&self, make: TokenStream, input: TokenStream) -> TokenStream {
+        let positional = self.fields.iter().filter(|field| field.positional).count();
+        let keywords = self.fields.iter().filter(|field| !field.positional).map(|field| &field.name);
+        let form = quote!(::rexp::expr::Form::#make(#input, #positional, &[#(#keywords),*])?);
+        if self.fields.is_empty() {
+            return quote!(#form;);
+        }
+        let mut index = 0usize;
+        let fields = self.fields.iter().map(|field| {
+            let (name, binding) = (&field.name, &field.binding);
+            let value = if field.positional {
+                index += 1;
+                let n = index - 1;
+                quote!(form.positional(#n))
+            } else {
+                quote!(form.keyword(#name))
+            };
+            let missing = match &field.default {
+                None => quote!(return ::std::result::Result::Err(::rexp::expr::FromSexpError::missing(#name))),
+                Some(Fallback::Trait) => quote!(::std::default::Default::default()),
+                Some(Fallback::Path(path)) => quote!(#path()),
+            };
+            quote! {
+                let #binding = match #value {
+                    ::std::option::Option::Some(sexp) => {
+                        ::rexp::expr::FromSexp::from_sexp(sexp).map_err(|e| e.within(#name))?
+                    }
+                    ::std::option::Option::None => #missing,
+                };
+            }
+        });
+        let fields = fields.collect::<Vec<_>>();
+        quote! {
+            let form = #form;
+            #(#fields)*
+        }
+    }
+}
+
+fn symbol(name: &str) -> TokenStream {
+    quote!(::rexp::expr::Sexp::Constant(::rexp::expr::Atom::Symbol(::std::string::String::from(#name))))
+}
+
+// `input`'s generics with every type parameter bounded by `bound`.
+fn bounded(input: &DeriveInput, bound: TokenStream) -> syn::Generics {
+    let mut generics = input.generics.clone();
+    let params = generics.type_params().map(|param| param.ident.clone()).collect::<Vec<_>>();
+    let clause = generics.make_where_clause();
+    for param in params {
+        clause.predicates.push(parse_quote!(#param: #bound));
+    }
+    generics
+}
+
+
+
+// Writing the impls
+
+fn to_sexp(input: DeriveInput) -> syn::Result<TokenStream> {
+    let body = match Shape::of(&input)? {
+        Shape::Newtype => quote!(::rexp::expr::ToSexp::to_sexp(&self.0)),
+        Shape::Struct(form) if form.fields.is_empty() => form.write(None),
+        Shape::Struct(form) => {
+            let (pattern, items) = (form.construct(quote!(Self)), form.write(None));
+            quote! {
+                let #pattern = self;
+                #items
+            }
+        }
+        Shape::Enum(variants) if variants.is_empty() => quote!(match *self {}),
+        Shape::Enum(variants) => {
+            let arms = variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let pattern = variant.form.construct(quote!(Self::#ident));
+                let sexp = match variant.form.kind {
+                    Kind::Unit => symbol(&variant.name),
+                    _ => variant.form.write(Some(symbol(&variant.name))),
+                };
+                quote!(#pattern => #sexp,)
+            });
+            quote!(match self { #(#arms)* })
+        }
+    };
+    let ident = &input.ident;
+    let generics = bounded(&input, quote!(::rexp::expr::ToSexp));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rexp::expr::ToSexp for #ident #ty_generics #where_clause {
+            fn to_sexp(&self) -> ::rexp::expr::Sexp {
+                #body
+            }
+        }
+    })
+}
+
+fn from_sexp(input: DeriveInput) -> syn::Result<TokenStream> {
+    let body = match Shape::of(&input)? {
+        Shape::Newtype => quote!(::rexp::expr::FromSexp::from_sexp(sexp).map(Self)),
+        Shape::Struct(form) => {
+            let (read, value) = (form.read(quote!(of), quote!(sexp)), form.construct(quote!(Self)));
+            quote! {
+                #read
+                ::std::result::Result::Ok(#value)
+            }
+        }
+        Shape::Enum(variants) => {
+            let arms = variants.iter().map(|variant| {
+                let (ident, name) = (&variant.ident, &variant.name);
+                let read = variant.form.read(quote!(new), quote!(items));
+                let value = variant.form.construct(quote!(Self::#ident));
+                quote! {
+                    #name => {
+                        #read
+                        ::std::result::Result::Ok(#value)
+                    }
+                }
+            });
+            let expected = match variants.len() {
+                0 => "no variants".to_owned(),
+                _ => {
+                    let names = variants.iter().map(|variant| format!("`{}`", variant.name)).collect::<Vec<_>>();
+                    format!("one of {}", names.join(", "))
+                }
+            };
+            quote! {
+                let (tag, items) = ::rexp::expr::Form::tagged(sexp)?;
+                match tag {
+                    #(#arms)*
+                    _ => ::std::result::Result::Err(::rexp::expr::FromSexpError::new(
+                        ::std::format!("unknown variant `{}`, expected {}", tag, #expected),
+                    )),
+                }
+            }
+        }
+    };
+    let ident = &input.ident;
+    let generics = bounded(&input, quote!(::rexp::expr::FromSexp));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rexp::expr::FromSexp for #ident #ty_generics #where_clause {
+            fn from_sexp(sexp: &::rexp::expr::Sexp) -> ::std::result::Result<Self, ::rexp::expr::FromSexpError> {
+                #body
+            }
+        }
+    })
+}
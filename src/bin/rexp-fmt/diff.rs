@@ -0,0 +1,244 @@
+/// Unified diffs by line, for `--diff`.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Edit<'a> {
+    Same(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+/// The changes from `old` to `new` as a unified diff with three lines of
+/// context, or an empty string if they're the same. Lines are compared with
+/// their `\n` or `\r\n`, so a change to just those still shows.
+pub fn unified(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    const CONTEXT: usize = 3;
+    let old: Vec<&str> = old.split_inclusive('\n').collect();
+    let new: Vec<&str> = new.split_inclusive('\n').collect();
+    let edits = edits(&old, &new);
+    let changes: Vec<usize> = edits.iter()
+        .enumerate()
+        .filter(|(_, edit)| !matches!(edit, Edit::Same(_)))
+        .map(|(n, _)| n)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    let mut n = 0;
+    while n < changes.len() {
+        // Changes close enough to share context go in one hunk
+        let mut last = n;
+        while last + 1 < changes.len() && changes[last + 1] - changes[last] <= 2 * CONTEXT {
+            last += 1;
+        }
+        let start = changes[n].saturating_sub(CONTEXT);
+        let end = (changes[last] + CONTEXT + 1).min(edits.len());
+        hunk(&mut out, &edits, start, end);
+        n = last + 1;
+    }
+    out
+}
+
+fn hunk(out: &mut String, edits: &[Edit], start: usize, end: usize) {
+    let old_before = edits[..start].iter().filter(|e| !matches!(e, Edit::Insert(_))).count();
+    let new_before = edits[..start].iter().filter(|e| !matches!(e, Edit::Delete(_))).count();
+    let old_len = edits[start..end].iter().filter(|e| !matches!(e, Edit::Insert(_))).count();
+    let new_len = edits[start..end].iter().filter(|e| !matches!(e, Edit::Delete(_))).count();
+    // An empty side is numbered by the line before it
+    let first = |before: usize, len: usize| if len == 0 { before } else { before + 1 };
+    out.push_str(&format!(
+        "@@ -{},{} +{},{} @@\n",
+        first(old_before, old_len), old_len,
+        first(new_before, new_len), new_len,
+    ));
+    for edit in &edits[start..end] {
+        let (mark, line) = match edit {
+            Edit::Same(line) => (' ', line),
+            Edit::Delete(line) => ('-', line),
+            Edit::Insert(line) => ('+', line),
+        };
+        out.push(mark);
+        out.push_str(line);
+        if !line.ends_with('\n') {
+            out.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+}
+
+// Past this many changed lines, finding the fewest edits costs more than it's
+// worth and the middle is replaced wholesale.
+const MAX_COST: usize = 1024;
+
+// A shortest edit script, by Myers' O(ND) algorithm.
+fn edits<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Edit<'a>> {
+    // Only the middle that differs needs searching
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut edits: Vec<Edit> = old[..prefix].iter().map(|line| Edit::Same(line)).collect();
+    match middle(a, b) {
+        Some(middle) => edits.extend(middle),
+        None => {
+            edits.extend(a.iter().map(|line| Edit::Delete(line)));
+            edits.extend(b.iter().map(|line| Edit::Insert(line)));
+        }
+    }
+    edits.extend(old[old.len() - suffix..].iter().map(|line| Edit::Same(line)));
+    edits
+}
+
+// `None` if it takes more than `MAX_COST` edits.
+fn middle<'a>(a: &[&'a str], b: &[&'a str]) -> Option<Vec<Edit<'a>>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    // `trace[d][k + d]` is how far along `a` the furthest path with `d` edits
+    // gets on diagonal `k`, where `k` is its `x - y`
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    // Whether the path to diagonal `k` after `d` edits comes down from `k + 1`,
+    // an insertion, rather than across from `k - 1`, a deletion
+    let down = |prev: &[isize], d: isize, k: isize| {
+        k == -d || (k != d && prev[(k - 1 + d - 1) as usize] < prev[(k + 1 + d - 1) as usize])
+    };
+    let mut found = false;
+    'search: for d in 0..=(n + m).min(MAX_COST as isize) {
+        let mut furthest = vec![0; 2 * d as usize + 1];
+        for k in (-d..=d).step_by(2) {
+            let mut x = match trace.last() {
+                None => 0,
+                Some(prev) if down(prev, d, k) => prev[(k + 1 + d - 1) as usize],
+                Some(prev) => prev[(k - 1 + d - 1) as usize] + 1,
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[(k + d) as usize] = x;
+            if x >= n && y >= m {
+                trace.push(furthest);
+                found = true;
+                break 'search;
+            }
+        }
+        trace.push(furthest);
+    }
+    if !found {
+        return None;
+    }
+
+    // Walk back from the end, taking the snake and then the edit before it
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..trace.len() as isize).rev() {
+        let prev = &trace[d as usize - 1];
+        let k = x - y;
+        let from = if down(prev, d, k) { k + 1 } else { k - 1 };
+        let from_x = prev[(from + d - 1) as usize];
+        let from_y = from_x - from;
+        while x > from_x && y > from_y {
+            edits.push(Edit::Same(a[x as usize - 1]));
+            x -= 1;
+            y -= 1;
+        }
+        if from == k + 1 {
+            edits.push(Edit::Insert(b[y as usize - 1]));
+        } else {
+            edits.push(Edit::Delete(a[x as usize - 1]));
+        }
+        x = from_x;
+        y = from_y;
+    }
+    while x > 0 && y > 0 {
+        edits.push(Edit::Same(a[x as usize - 1]));
+        x -= 1;
+        y -= 1;
+    }
+    edits.reverse();
+    Some(edits)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_text_has_no_diff() {
+        assert_eq!(unified("a\nb\n", "a\nb\n", "x", "y"), "");
+    }
+
+    #[test]
+    fn changes_get_context() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+        assert_eq!(
+            unified(old, new, "old", "new"),
+            "--- old\n+++ new\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn far_apart_changes_get_their_own_hunks() {
+        let old: String = (1..=20).map(|n| format!("{}\n", n)).collect();
+        let new: String = (1..=20)
+            .filter(|&n| n != 19)
+            .map(|n| if n == 2 { "two\n".to_owned() } else { format!("{}\n", n) })
+            .collect();
+        let diff = unified(&old, &new, "old", "new");
+        assert_eq!(diff.matches("@@ -").count(), 2, "{}", diff);
+        assert!(diff.contains("@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n"));
+        assert!(diff.contains("@@ -16,5 +16,4 @@\n 16\n 17\n 18\n-19\n 20\n"));
+    }
+
+    #[test]
+    fn line_endings_count() {
+        assert_eq!(
+            unified("a\nb", "a\nb\n", "old", "new"),
+            "--- old\n+++ new\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+b\n"
+        );
+        assert_eq!(
+            unified("a\r\nb\r\n", "a\nb\r\n", "old", "new"),
+            "--- old\n+++ new\n@@ -1,2 +1,2 @@\n-a\r\n+a\n b\r\n"
+        );
+    }
+
+    #[test]
+    fn edits_are_shortest() {
+        let old = ["a", "b", "c", "a", "b", "b", "a"];
+        let new = ["c", "b", "a", "b", "a", "c"];
+        let edits = edits(&old, &new);
+        // The usual example has five deletions and insertions at best
+        assert_eq!(edits.iter().filter(|e| !matches!(e, Edit::Same(_))).count(), 5);
+        let (mut old_side, mut new_side) = (Vec::new(), Vec::new());
+        for edit in &edits {
+            match *edit {
+                Edit::Same(line) => {
+                    old_side.push(line);
+                    new_side.push(line);
+                }
+                Edit::Delete(line) => old_side.push(line),
+                Edit::Insert(line) => new_side.push(line),
+            }
+        }
+        assert_eq!((old_side, new_side), (old.to_vec(), new.to_vec()));
+    }
+
+    #[test]
+    fn big_rewrites_are_replaced_whole() {
+        let old: String = (0..MAX_COST).map(|n| format!("{}\n", n)).collect();
+        let new: String = (0..MAX_COST).map(|n| format!("new {}\n", n)).collect();
+        let diff = unified(&old, &new, "old", "new");
+        let hunk = format!("@@ -1,{0} +1,{0} @@\n-0\n-1\n", MAX_COST);
+        assert!(diff.starts_with(&format!("--- old\n+++ new\n{}", hunk)));
+    }
+
+    #[test]
+    fn insertions_into_nothing() {
+        assert_eq!(unified("", "a\n", "old", "new"), "--- old\n+++ new\n@@ -0,0 +1,1 @@\n+a\n");
+    }
+}
@@ -0,0 +1,213 @@
+/// `rexp-fmt`, which rewrites sexp files in one canonical layout, keeping
+/// their comments.
+///
+/// Settings come from the nearest `rexp-fmt.toml` in the current directory
+/// or above it, for example:
+///
+/// ```toml
+/// width = 100   # columns to fit in
+/// indent = 2    # how far bodies are indented
+///
+/// # Heads laid out like `define`, with this many arguments on the first line,
+/// # or "align" to line up every argument
+/// [forms]
+/// define-rule = 1
+/// define = "align"
+/// ```
+use std::{
+    env,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process,
+};
+
+use rexp::pretty::{self, Indent, Options};
+
+mod diff;
+
+const USAGE: &str = "\
+usage: rexp-fmt [--check | --diff] [--config FILE] [FILE...]
+
+Formats each FILE in place, or stdin to stdout if there are none.
+
+  --check        list the files that aren't formatted, and change nothing
+  --diff         show what formatting would change, and change nothing
+  --config FILE  read settings from FILE instead of the nearest rexp-fmt.toml
+
+Exits with 1 if --check or --diff found something to change, or 2 on errors.
+";
+
+const CONFIG_FILE: &str = "rexp-fmt.toml";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Mode {
+    Write,
+    Check,
+    Diff,
+}
+
+fn main() {
+    let code = run(env::args().skip(1).collect()).unwrap_or_else(|e| {
+        eprintln!("rexp-fmt: {}", e);
+        2
+    });
+    process::exit(code)
+}
+
+fn run(args: Vec<String>) -> Result<i32, String> {
+    let mut mode = Mode::Write;
+    let mut config = None;
+    let mut files = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => mode = Mode::Check,
+            "--diff" => mode = Mode::Diff,
+            "--config" => config = Some(PathBuf::from(args.next().ok_or("--config needs a file")?)),
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                return Ok(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{}`\n\n{}", arg, USAGE)),
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+
+    let config = match config {
+        Some(path) => Some(path),
+        None => find_config(&env::current_dir().map_err(|e| e.to_string())?),
+    };
+    let options = match config {
+        Some(path) => load_config(&path)?,
+        None => Options::default(),
+    };
+
+    if files.is_empty() {
+        let mut src = String::new();
+        io::stdin().read_to_string(&mut src).map_err(|e| format!("<stdin>: {}", e))?;
+        let formatted = pretty::format(&src, &options).map_err(|e| format!("<stdin>: {}", e))?;
+        return Ok(match mode {
+            Mode::Write => {
+                print!("{}", formatted);
+                0
+            }
+            _ => report(mode, "<stdin>", &src, &formatted),
+        });
+    }
+    Ok(files.iter().map(|file| format_file(file, mode, &options)).max().unwrap_or(0))
+}
+
+// Format one file, giving the exit code for it.
+fn format_file(path: &Path, mode: Mode, options: &Options) -> i32 {
+    let name = path.display().to_string();
+    let src = match fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(e) => {
+            eprintln!("{}: {}", name, e);
+            return 2;
+        }
+    };
+    let formatted = match pretty::format(&src, options) {
+        Ok(formatted) => formatted,
+        Err(e) => {
+            eprintln!("{}: {}", name, e);
+            return 2;
+        }
+    };
+    match mode {
+        Mode::Write if formatted != src => match fs::write(path, formatted) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                2
+            }
+        },
+        Mode::Write => 0,
+        _ => report(mode, &name, &src, &formatted),
+    }
+}
+
+// Tell `--check` or `--diff` whether `src` would change.
+fn report(mode: Mode, name: &str, src: &str, formatted: &str) -> i32 {
+    if src == formatted {
+        return 0;
+    }
+    match mode {
+        Mode::Diff => print!("{}", diff::unified(src, formatted, name, &format!("{} (formatted)", name))),
+        _ => println!("{}", name),
+    }
+    1
+}
+
+// The nearest config file in `dir` or above it.
+fn find_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().map(|d| d.join(CONFIG_FILE)).find(|path| path.is_file())
+}
+
+fn load_config(path: &Path) -> Result<Options, String> {
+    fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| parse_config(&text))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn parse_config(text: &str) -> Result<Options, String> {
+    use toml::Value;
+    let table: toml::value::Table = toml::from_str(text).map_err(|e| e.to_string())?;
+    let mut options = Options::default();
+    let count = |key: &str, value: &Value| match value {
+        Value::Integer(n) if *n >= 0 => Ok(*n as usize),
+        _ => Err(format!("`{}` should be a whole number", key)),
+    };
+    for (key, value) in &table {
+        match key.as_str() {
+            "width" => options.width = count(key, value)?,
+            "indent" => options.body_indent = count(key, value)?,
+            "forms" => {
+                let forms = value.as_table().ok_or("`forms` should be a table")?;
+                for (head, rule) in forms {
+                    let indent = match rule {
+                        Value::String(s) if s == "align" => Indent::Align,
+                        rule => Indent::Body(count(&format!("forms.{}", head), rule)
+                            .map_err(|e| format!("{} or \"align\"", e))?),
+                    };
+                    options.forms.insert(head.clone(), indent);
+                }
+            }
+            _ => return Err(format!("unknown setting `{}`", key)),
+        }
+    }
+    Ok(options)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_settings() {
+        let options = parse_config(
+            "width = 100\nindent = 4\n[forms]\ndefine-rule = 1\ndefine = \"align\"\n"
+        ).unwrap();
+        assert_eq!((options.width, options.body_indent), (100, 4));
+        assert_eq!(options.forms["define-rule"], Indent::Body(1));
+        assert_eq!(options.forms["define"], Indent::Align);
+        // Forms not mentioned keep their defaults
+        assert_eq!(options.forms["lambda"], Indent::Body(1));
+        assert_eq!(parse_config(""), Ok(Options::default()));
+    }
+
+    #[test]
+    fn config_errors() {
+        assert_eq!(parse_config("colour = 1"), Err("unknown setting `colour`".to_owned()));
+        assert_eq!(parse_config("width = -1"), Err("`width` should be a whole number".to_owned()));
+        assert_eq!(
+            parse_config("[forms]\nlet = \"body\""),
+            Err("`forms.let` should be a whole number or \"align\"".to_owned())
+        );
+        assert!(parse_config("width = ").is_err());
+    }
+}
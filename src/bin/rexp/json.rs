@@ -0,0 +1,171 @@
+/// Converting datums to and from JSON.
+///
+/// Lists are arrays, symbols are strings, and integers, finite floats and
+/// booleans are themselves. Everything else is an object with one key
+/// naming what it is:
+///
+/// - `{"string": "text"}` and `{"char": "c"}`
+/// - `{"number": "1/3"}` for numbers JSON can't hold, like ratios and `+inf.0`
+/// - `{"vector": [...]}`
+/// - `{"dotted": [a, b, tail]}` for `(a b . tail)`
+/// - `{"quote": x}`, `{"quasiquote": x}`, `{"unquote": x}` and `{"unquote-splicing": x}`
+use serde_json::{json, Map, Number, Value};
+
+use rexp::{
+    expr::{Atom, Num, Quote, Sexp},
+    parse,
+};
+
+pub fn to_json(sexp: &Sexp) -> Value {
+    let all = |items: &[Sexp]| items.iter().map(to_json).collect::<Vec<_>>();
+    match sexp {
+        Sexp::Constant(Atom::Num(Num::Int(i))) => json!(i),
+        Sexp::Constant(Atom::Num(Num::Float(x))) => match Number::from_f64(*x) {
+            Some(x) => Value::Number(x),
+            None => json!({ "number": sexp.to_string() }),
+        },
+        Sexp::Constant(Atom::Num(_)) => json!({ "number": sexp.to_string() }),
+        Sexp::Constant(Atom::Bool(b)) => json!(b),
+        Sexp::Constant(Atom::Char(c)) => json!({ "char": c.to_string() }),
+        Sexp::Constant(Atom::Symbol(s)) => json!(s),
+        Sexp::Constant(Atom::String(s)) => json!({ "string": s }),
+        Sexp::List(items) => Value::Array(all(items)),
+        Sexp::DottedList(items, tail) => {
+            let mut items = all(items);
+            items.push(to_json(tail));
+            json!({ "dotted": items })
+        }
+        Sexp::Vector(items) => json!({ "vector": all(items) }),
+        Sexp::Quote(q) => {
+            let (key, s) = match q {
+                Quote::Quote(s) => ("quote", s),
+                Quote::Quasi(s) => ("quasiquote", s),
+                Quote::UnQuote(s) => ("unquote", s),
+                Quote::Splice(s) => ("unquote-splicing", s),
+            };
+            json!({ key: to_json(s) })
+        }
+    }
+}
+
+pub fn from_json(value: &Value) -> Result<Sexp, String> {
+    let all = |items: &[Value]| items.iter().map(from_json).collect::<Result<Vec<_>, _>>();
+    Ok(match value {
+        Value::Null => return Err("null has no sexp form".to_owned()),
+        Value::Bool(b) => Sexp::Constant(Atom::Bool(*b)),
+        Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => Sexp::Constant(Atom::Num(Num::Int(i))),
+            // Too big for an `i64`, so `bigint` decides what becomes of it
+            _ if n.is_u64() => number(&n.to_string())?,
+            (_, Some(x)) => Sexp::Constant(Atom::Num(Num::Float(x))),
+            _ => return Err(format!("can't read the number {}", n)),
+        },
+        Value::String(s) => Sexp::Constant(Atom::Symbol(s.clone())),
+        Value::Array(items) => Sexp::List(all(items)?),
+        Value::Object(map) => return tagged(map),
+    })
+}
+
+fn tagged(map: &Map<String, Value>) -> Result<Sexp, String> {
+    let mut entries = map.iter();
+    let (key, value) = match (entries.next(), entries.next()) {
+        (Some(entry), None) => entry,
+        _ => return Err(format!("expected an object with one key, found {}", Value::Object(map.clone()))),
+    };
+    let text = || value.as_str().ok_or_else(|| format!("`{}` should be a string", key));
+    let items = || value.as_array().ok_or_else(|| format!("`{}` should be an array", key));
+    let quoted = || from_json(value).map(Box::new);
+    Ok(match key.as_str() {
+        "string" => Sexp::Constant(Atom::String(text()?.to_owned())),
+        "char" => {
+            let mut chars = text()?.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Sexp::Constant(Atom::Char(c)),
+                _ => return Err("`char` should be a single character".to_owned()),
+            }
+        }
+        "number" => number(text()?)?,
+        "vector" => Sexp::Vector(items()?.iter().map(from_json).collect::<Result<_, _>>()?),
+        "dotted" => {
+            let mut items = items()?.iter().map(from_json).collect::<Result<Vec<_>, _>>()?;
+            let tail = match items.pop() {
+                Some(tail) if !items.is_empty() => tail,
+                _ => return Err("`dotted` needs an item and a tail".to_owned()),
+            };
+            // The same as reading `(a . tail)`, so a list tail is spliced in
+            parse::dotted(items, tail)
+        }
+        "quote" => Sexp::Quote(Quote::Quote(quoted()?)),
+        "quasiquote" => Sexp::Quote(Quote::Quasi(quoted()?)),
+        "unquote" => Sexp::Quote(Quote::UnQuote(quoted()?)),
+        "unquote-splicing" => Sexp::Quote(Quote::Splice(quoted()?)),
+        _ => return Err(format!("unknown key `{}`", key)),
+    })
+}
+
+// Read `text` as a number literal.
+fn number(text: &str) -> Result<Sexp, String> {
+    match text.parse::<Sexp>() {
+        Ok(sexp @ Sexp::Constant(Atom::Num(_))) => Ok(sexp),
+        Ok(_) => Err(format!("`{}` isn't a number", text)),
+        Err(e) => Err(format!("`{}`: {}", text, e.kind())),
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sexp(src: &str) -> Sexp {
+        src.parse().unwrap()
+    }
+
+    #[test]
+    fn plain_values() {
+        assert_eq!(
+            to_json(&sexp("(define x 1 2.5 #t name)")),
+            json!(["define", "x", 1, 2.5, true, "name"])
+        );
+    }
+
+    #[test]
+    fn tagged_values() {
+        assert_eq!(
+            to_json(&sexp("(\"s\" #\\a 1/3 +inf.0 #(1) (a . b) 'q `(,u ,@v))")),
+            json!([
+                { "string": "s" },
+                { "char": "a" },
+                { "number": "1/3" },
+                { "number": "+inf.0" },
+                { "vector": [1] },
+                { "dotted": ["a", "b"] },
+                { "quote": "q" },
+                { "quasiquote": [{ "unquote": "u" }, { "unquote-splicing": "v" }] },
+            ])
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let src = "(define (f . xs) #(\"a\" #\\space 1.5 -7 1/2 -inf.0) `(,@xs ,(g 'y)) (a b . c) ())";
+        assert_eq!(from_json(&to_json(&sexp(src))), Ok(sexp(src)));
+    }
+
+    #[test]
+    fn bad_json() {
+        assert!(from_json(&json!(null)).is_err());
+        assert!(from_json(&json!({ "string": 1 })).is_err());
+        assert!(from_json(&json!({ "char": "ab" })).is_err());
+        assert!(from_json(&json!({ "number": "x" })).is_err());
+        assert!(from_json(&json!({ "dotted": ["a"] })).is_err());
+        assert!(from_json(&json!({ "string": "a", "char": "b" })).is_err());
+        assert!(from_json(&json!({ "what": 1 })).is_err());
+    }
+
+    #[test]
+    fn dotted_list_tails_are_spliced() {
+        assert_eq!(from_json(&json!({ "dotted": ["a", ["b"]] })), Ok(sexp("(a b)")));
+    }
+}
@@ -0,0 +1,208 @@
+/// `rexp`, for checking, printing, converting and querying sexp files from
+/// the shell.
+use std::{
+    env,
+    fs,
+    io::{self, Read},
+    process,
+};
+
+use rexp::{
+    expr::Sexp,
+    parse,
+    pretty::{self, Options},
+};
+
+mod json;
+mod query;
+
+const USAGE: &str = "\
+usage: rexp <command> [FILE...]
+
+Reads each FILE, or stdin if there are none or for `-`.
+
+commands:
+  check [FILE...]              report any parse errors with their line and column
+  print [--width N] [FILE...]  pretty print every datum
+  to-json [--pretty] [FILE]    write the datums as a JSON array
+  from-json [FILE]             read a JSON array back into datums
+  query PATH [FILE...]         print the datums at PATH, like `define/1`
+
+A PATH is steps split by `/`: a number picks that item of each list, `*`
+picks every item, and a symbol picks the lists it is the head of.
+
+Exits with 1 if some input doesn't parse or a query finds nothing, and with 2
+if the command couldn't run at all.
+";
+
+// The ways a command can fail, and what they exit with.
+enum Failure {
+    // Bad input, or nothing found
+    Input(String),
+    // Bad arguments, or a file that can't be read
+    Run(String),
+}
+
+fn main() {
+    let code = match run(env::args().skip(1).collect()) {
+        Ok(()) => 0,
+        Err(Failure::Input(e)) => {
+            if !e.is_empty() {
+                eprintln!("{}", e);
+            }
+            1
+        }
+        Err(Failure::Run(e)) => {
+            if !e.is_empty() {
+                eprintln!("rexp: {}", e);
+            }
+            2
+        }
+    };
+    process::exit(code)
+}
+
+fn run(args: Vec<String>) -> Result<(), Failure> {
+    let mut args = args.into_iter();
+    let command = args.next().ok_or_else(|| Failure::Run(format!("missing command\n\n{}", USAGE)))?;
+    let mut width = Options::default().width;
+    let mut pretty_json = false;
+    let mut rest = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" if command == "print" => {
+                width = args.next()
+                    .and_then(|n| n.parse().ok())
+                    .ok_or_else(|| Failure::Run("--width needs a number".to_owned()))?;
+            }
+            "--pretty" if command == "to-json" => pretty_json = true,
+            "-" => rest.push(arg),
+            _ if arg.starts_with('-') => {
+                return Err(Failure::Run(format!("unknown option `{}`\n\n{}", arg, USAGE)));
+            }
+            _ => rest.push(arg),
+        }
+    }
+
+    match command.as_str() {
+        "check" => check(&rest),
+        "print" => {
+            let options = Options { width, ..Options::default() };
+            for sexp in read_all(&rest)? {
+                println!("{}", pretty::pretty(&sexp, &options));
+            }
+            Ok(())
+        }
+        "to-json" => {
+            let datums = read_all(at_most_one(&rest)?)?;
+            let value = serde_json::Value::Array(datums.iter().map(json::to_json).collect());
+            if pretty_json {
+                println!("{:#}", value);
+            } else {
+                println!("{}", value);
+            }
+            Ok(())
+        }
+        "from-json" => {
+            let (name, text) = read(at_most_one(&rest)?.first().map_or("-", String::as_str))?;
+            let value: serde_json::Value = serde_json::from_str(&text)
+                .map_err(|e| Failure::Input(format!("{}: {}", name, e)))?;
+            let items = value.as_array()
+                .ok_or_else(|| Failure::Input(format!("{}: expected an array of datums", name)))?;
+            for item in items {
+                let sexp = json::from_json(item).map_err(|e| Failure::Input(format!("{}: {}", name, e)))?;
+                println!("{}", pretty::pretty(&sexp, &Options::default()));
+            }
+            Ok(())
+        }
+        "query" => {
+            let (path, files) = rest.split_first()
+                .ok_or_else(|| Failure::Run("query needs a path".to_owned()))?;
+            let path = query::parse_path(path).map_err(Failure::Run)?;
+            let top = read_all(files)?;
+            let selected = query::select(&top, &path);
+            for sexp in &selected {
+                println!("{}", sexp);
+            }
+            if selected.is_empty() {
+                return Err(Failure::Input(String::new()));
+            }
+            Ok(())
+        }
+        "help" | "-h" | "--help" => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(Failure::Run(format!("unknown command `{}`\n\n{}", command, USAGE))),
+    }
+}
+
+// Parse every file, reporting all of the errors rather than just the first.
+// Every file is checked, even after one can't be read, which still makes it
+// exit with 2.
+fn check(files: &[String]) -> Result<(), Failure> {
+    let (mut failed, mut unreadable) = (false, false);
+    for file in stdin_if_none(files) {
+        let (name, text) = match read(file) {
+            Ok(read) => read,
+            Err(Failure::Run(e)) | Err(Failure::Input(e)) => {
+                eprintln!("rexp: {}", e);
+                unreadable = true;
+                continue;
+            }
+        };
+        if let Err(e) = parse::parse_all(&text) {
+            eprintln!("{}", located(&name, &e));
+            failed = true;
+        }
+    }
+    if unreadable {
+        return Err(Failure::Run(String::new()));
+    }
+    if failed {
+        return Err(Failure::Input(String::new()));
+    }
+    Ok(())
+}
+
+// The top level datums of every file, one after another.
+fn read_all(files: &[String]) -> Result<Vec<Sexp>, Failure> {
+    let mut datums = Vec::new();
+    for file in stdin_if_none(files) {
+        let (name, text) = read(file)?;
+        datums.extend(parse::parse_all(&text).map_err(|e| Failure::Input(located(&name, &e)))?);
+    }
+    Ok(datums)
+}
+
+fn stdin_if_none(files: &[String]) -> Vec<&str> {
+    if files.is_empty() {
+        vec!["-"]
+    } else {
+        files.iter().map(String::as_str).collect()
+    }
+}
+
+fn at_most_one(files: &[String]) -> Result<&[String], Failure> {
+    if files.len() > 1 {
+        return Err(Failure::Run("this command takes one file at most".to_owned()));
+    }
+    Ok(files)
+}
+
+// The name to report `file` by, and what's in it.
+fn read(file: &str) -> Result<(String, String), Failure> {
+    let mut text = String::new();
+    let (name, result) = if file == "-" {
+        ("<stdin>", io::stdin().read_to_string(&mut text).map(|_| ()))
+    } else {
+        (file, fs::read_to_string(file).map(|t| text = t))
+    };
+    result.map_err(|e| Failure::Run(format!("{}: {}", name, e)))?;
+    Ok((name.to_owned(), text))
+}
+
+// `file:line:column: message`, the way compilers and editors expect.
+fn located(name: &str, e: &rexp::Error) -> String {
+    format!("{}:{}:{}: {}", name, e.line(), e.column(), e.kind())
+}
@@ -0,0 +1,103 @@
+/// Selecting datums by path, for `rexp query`.
+///
+/// A path is a list of steps split by `/`, each taking the datums selected so
+/// far to some of their items, starting from the top level of the file:
+///
+/// - a number `n` picks the `n`th item, counting from 0
+/// - `*` picks every item
+/// - anything else picks the lists whose head is that symbol
+///
+/// A list's head is its item 0, so `*` picks it too. So `define/1` is the
+/// name, or name and parameters, of each top level `define`, and
+/// `package/dependencies/*` is the symbol `dependencies` followed by
+/// everything after it in the `(dependencies ...)` lists of each `(package ...)`.
+use rexp::expr::{Atom, Sexp};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Step {
+    Index(usize),
+    All,
+    Head(String),
+}
+
+pub fn parse_path(path: &str) -> Result<Vec<Step>, String> {
+    path.split('/')
+        .map(|step| match step {
+            "" => Err(format!("empty step in path `{}`", path)),
+            "*" => Ok(Step::All),
+            _ => Ok(step.parse().map_or_else(|_| Step::Head(step.to_owned()), Step::Index)),
+        })
+        .collect()
+}
+
+/// The datums that `path` leads to from `top`, in source order.
+pub fn select<'a>(top: &'a [Sexp], path: &[Step]) -> Vec<&'a Sexp> {
+    let mut steps = path.iter();
+    let mut selected: Vec<&Sexp> = match steps.next() {
+        Some(step) => take(top, step),
+        None => return top.iter().collect(),
+    };
+    for step in steps {
+        selected = selected.into_iter().flat_map(|sexp| take(items(sexp), step)).collect();
+    }
+    selected
+}
+
+// The items of a list or vector, and nothing for anything else.
+fn items(sexp: &Sexp) -> &[Sexp] {
+    match sexp {
+        Sexp::List(items) | Sexp::DottedList(items, _) | Sexp::Vector(items) => items,
+        _ => &[],
+    }
+}
+
+fn take<'a>(items: &'a [Sexp], step: &Step) -> Vec<&'a Sexp> {
+    match step {
+        Step::Index(n) => items.get(*n).into_iter().collect(),
+        Step::All => items.iter().collect(),
+        Step::Head(head) => items
+            .iter()
+            .filter(|item| matches!(
+                self::items(item).first(),
+                Some(Sexp::Constant(Atom::Symbol(s))) if s == head
+            ))
+            .collect(),
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(src: &str, path: &str) -> Vec<String> {
+        let top = rexp::parse::parse_all(src).unwrap();
+        select(&top, &parse_path(path).unwrap()).iter().map(|s| s.to_string()).collect()
+    }
+
+    const SRC: &str = "(package (name rexp) (dependencies nom proptest))\n\
+                       (define (f x) x)\n(define y 2)\n#(a b)";
+
+    #[test]
+    fn steps() {
+        assert_eq!(query(SRC, "define/1"), vec!["(f x)", "y"]);
+        assert_eq!(query(SRC, "package/dependencies/*"), vec!["dependencies", "nom", "proptest"]);
+        assert_eq!(query(SRC, "package/name/1"), vec!["rexp"]);
+        assert_eq!(query(SRC, "3/1"), vec!["b"]);
+        assert_eq!(query(SRC, "*/0"), vec!["package", "define", "define", "a"]);
+    }
+
+    #[test]
+    fn nothing_selected() {
+        assert!(query(SRC, "define/9").is_empty());
+        assert!(query(SRC, "lambda").is_empty());
+        assert!(query(SRC, "define/2/0").is_empty());
+    }
+
+    #[test]
+    fn bad_paths() {
+        assert!(parse_path("define//1").is_err());
+        assert!(parse_path("").is_err());
+    }
+}
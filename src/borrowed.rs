@@ -0,0 +1,127 @@
+/// Zero-copy variant of the Symbolic Expression types.
+///
+/// Symbols and strings borrow from the source they were read from, and are
+/// only copied when unescaping changed them.
+use std::borrow::Cow;
+
+use crate::expr::{self, Num};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Atom<'a> {
+    Num(Num),
+    Bool(bool),
+    Char(char),
+    Symbol(Cow<'a, str>),
+    String(Cow<'a, str>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Quote<'a> {
+    Quote(Box<Sexp<'a>>),
+    Quasi(Box<Sexp<'a>>),
+    UnQuote(Box<Sexp<'a>>),
+    Splice(Box<Sexp<'a>>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Sexp<'a> {
+    Quote(Quote<'a>),
+    Constant(Atom<'a>),
+    List(Vec<Sexp<'a>>),
+    /// The tail is never a `List` or `DottedList` itself.
+    DottedList(Vec<Sexp<'a>>, Box<Sexp<'a>>),
+    Vector(Vec<Sexp<'a>>),
+}
+
+impl<'a> Atom<'a> {
+    /// Copy out anything still borrowed.
+    pub fn into_owned(self) -> expr::Atom {
+        match self {
+            Atom::Num(n) => expr::Atom::Num(n),
+            Atom::Bool(b) => expr::Atom::Bool(b),
+            Atom::Char(c) => expr::Atom::Char(c),
+            Atom::Symbol(s) => expr::Atom::Symbol(s.into_owned()),
+            Atom::String(s) => expr::Atom::String(s.into_owned()),
+        }
+    }
+}
+
+impl<'a> Quote<'a> {
+    pub fn into_owned(self) -> expr::Quote {
+        let owned = |s: Box<Sexp<'a>>| Box::new(s.into_owned());
+        match self {
+            Quote::Quote(s) => expr::Quote::Quote(owned(s)),
+            Quote::Quasi(s) => expr::Quote::Quasi(owned(s)),
+            Quote::UnQuote(s) => expr::Quote::UnQuote(owned(s)),
+            Quote::Splice(s) => expr::Quote::Splice(owned(s)),
+        }
+    }
+}
+
+impl<'a> Sexp<'a> {
+    /// The same tree as an `expr::Sexp`, copying out anything still borrowed.
+    pub fn into_owned(self) -> expr::Sexp {
+        fn owned<'a>(items: Vec<Sexp<'a>>) -> Vec<expr::Sexp> {
+            items.into_iter().map(Sexp::into_owned).collect()
+        }
+        match self {
+            Sexp::Quote(q) => expr::Sexp::Quote(q.into_owned()),
+            Sexp::Constant(a) => expr::Sexp::Constant(a.into_owned()),
+            Sexp::List(items) => expr::Sexp::List(owned(items)),
+            Sexp::DottedList(items, tail) => {
+                expr::Sexp::DottedList(owned(items), Box::new(tail.into_owned()))
+            }
+            Sexp::Vector(items) => expr::Sexp::Vector(owned(items)),
+        }
+    }
+}
+
+impl<'a> From<Sexp<'a>> for expr::Sexp {
+    fn from(sexp: Sexp<'a>) -> expr::Sexp {
+        sexp.into_owned()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::borrowed::parse;
+
+    #[test]
+    fn plain_text_is_borrowed() {
+        let src = String::from("(define |a b| \"text\")");
+        match parse(&src) {
+            Ok(Sexp::List(items)) => {
+                for item in &items {
+                    match item {
+                        Sexp::Constant(Atom::Symbol(s)) | Sexp::Constant(Atom::String(s)) => {
+                            assert!(matches!(s, Cow::Borrowed(_)), "{:?}", s)
+                        }
+                        _ => panic!("{:?}", item),
+                    }
+                }
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn escaped_text_is_owned() {
+        assert!(matches!(
+            parse(r#""a\tb""#),
+            Ok(Sexp::Constant(Atom::String(Cow::Owned(ref s)))) if s == "a\tb"
+        ));
+        assert!(matches!(
+            parse(r"|a\x41;|"),
+            Ok(Sexp::Constant(Atom::Symbol(Cow::Owned(ref s)))) if s == "aA"
+        ));
+    }
+
+    #[test]
+    fn into_owned_matches_the_owned_parser() {
+        let src = "(a `(b ,c ,@d) #(1 2.5 #\\x) \"s\\n\" (e . f) |g h|)";
+        assert_eq!(parse(src).map(Sexp::into_owned), crate::parse::parse(src));
+    }
+}
@@ -0,0 +1,291 @@
+/// A lossless concrete syntax tree, for tools like formatters and refactorings
+/// that have to keep the comments and layout of the source.
+///
+/// Every byte of the source belongs to exactly one token or one piece of
+/// trivia, and trivia is attached to the token that follows it, so printing
+/// a tree with `Display` gives back the source it was read from.
+use std::fmt;
+
+use crate::{
+    error::Error,
+    expr::{self, Sexp},
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TokenKind {
+    /// `(`
+    Open,
+    /// `#(`
+    VectorOpen,
+    /// `)`
+    Close,
+    /// The `.` before the tail of a dotted list.
+    Dot,
+    /// `'`
+    Quote,
+    /// `` ` ``
+    Quasi,
+    /// `,`
+    UnQuote,
+    /// `,@`
+    Splice,
+    /// A number, boolean, character, symbol or string, spelled as in the source.
+    Atom,
+}
+
+/// The parts of the source that the reader skips over.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Trivia {
+    Whitespace(String),
+    /// From the `;` up to, but not including, the newline.
+    LineComment(String),
+    /// From `#|` to the matching `|#`.
+    BlockComment(String),
+    /// The datum after a `#;`.
+    DatumComment(Box<Node>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    /// The trivia between the previous token and this one.
+    pub leading: Vec<Trivia>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Node {
+    Atom(Token),
+    /// A quote prefix and the datum it quotes.
+    Quote(Token, Box<Node>),
+    List {
+        open: Token,
+        items: Vec<Node>,
+        /// The `.` and the datum after it in a dotted list.
+        tail: Option<(Token, Box<Node>)>,
+        close: Token,
+    },
+    Vector {
+        open: Token,
+        items: Vec<Node>,
+        close: Token,
+    },
+}
+
+/// A whole source text.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Root {
+    pub datums: Vec<Node>,
+    /// The trivia after the last datum.
+    pub trailing: Vec<Trivia>,
+}
+
+impl Token {
+    /// A token with no trivia before it.
+    pub fn new(kind: TokenKind, text: &str) -> Token {
+        Token { kind, text: text.to_owned(), leading: Vec::new() }
+    }
+}
+
+impl Node {
+    /// The token this datum starts with, which holds the trivia before it.
+    pub fn first_token(&self) -> &Token {
+        match self {
+            Node::Atom(token) | Node::Quote(token, _) => token,
+            Node::List { open, .. } | Node::Vector { open, .. } => open,
+        }
+    }
+
+    pub fn first_token_mut(&mut self) -> &mut Token {
+        match self {
+            Node::Atom(token) | Node::Quote(token, _) => token,
+            Node::List { open, .. } | Node::Vector { open, .. } => open,
+        }
+    }
+
+    /// Call `f` on every token of this datum in source order, skipping the
+    /// ones inside `#;` comments.
+    pub fn for_each_token_mut<F: FnMut(&mut Token)>(&mut self, f: &mut F) {
+        match self {
+            Node::Atom(token) => f(token),
+            Node::Quote(prefix, datum) => {
+                f(prefix);
+                datum.for_each_token_mut(f);
+            }
+            Node::List { open, items, tail, close } => {
+                f(open);
+                items.iter_mut().for_each(|item| item.for_each_token_mut(f));
+                if let Some((dot, tail)) = tail {
+                    f(dot);
+                    tail.for_each_token_mut(f);
+                }
+                f(close);
+            }
+            Node::Vector { open, items, close } => {
+                f(open);
+                items.iter_mut().for_each(|item| item.for_each_token_mut(f));
+                f(close);
+            }
+        }
+    }
+
+    /// The datum as an `expr::Sexp`, dropping the trivia.
+    ///
+    /// Atoms are read again from their text, so this only fails if a token
+    /// was edited into something that doesn't read.
+    pub fn to_sexp(&self) -> Result<Sexp, Error> {
+        let all = |items: &[Node]| items.iter().map(Node::to_sexp).collect::<Result<Vec<_>, _>>();
+        Ok(match self {
+            Node::Atom(token) => token.text.parse()?,
+            Node::Quote(prefix, datum) => {
+                let datum = Box::new(datum.to_sexp()?);
+                Sexp::Quote(match prefix.kind {
+                    TokenKind::Quasi => expr::Quote::Quasi(datum),
+                    TokenKind::UnQuote => expr::Quote::UnQuote(datum),
+                    TokenKind::Splice => expr::Quote::Splice(datum),
+                    _ => expr::Quote::Quote(datum),
+                })
+            }
+            Node::List { items, tail: None, .. } => Sexp::List(all(items)?),
+            Node::List { items, tail: Some((_, tail)), .. } => {
+                crate::parse::dotted(all(items)?, tail.to_sexp()?)
+            }
+            Node::Vector { items, .. } => Sexp::Vector(all(items)?),
+        })
+    }
+}
+
+impl Root {
+    /// Call `f` on every token in source order, skipping the ones inside
+    /// `#;` comments.
+    pub fn for_each_token_mut<F: FnMut(&mut Token)>(&mut self, mut f: F) {
+        self.datums.iter_mut().for_each(|datum| datum.for_each_token_mut(&mut f));
+    }
+
+    /// The top level datums as `expr::Sexp`s, the same as `parse::parse_all` gives.
+    pub fn to_sexps(&self) -> Result<Vec<Sexp>, Error> {
+        self.datums.iter().map(Node::to_sexp).collect()
+    }
+}
+
+// Printing gives back the source byte for byte.
+
+impl fmt::Display for Trivia {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Trivia::Whitespace(s) | Trivia::LineComment(s) | Trivia::BlockComment(s) => {
+                write!(f, "{}", s)
+            }
+            Trivia::DatumComment(datum) => write!(f, "#;{}", datum),
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for trivia in &self.leading {
+            write!(f, "{}", trivia)?;
+        }
+        write!(f, "{}", self.text)
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Node::Atom(token) => write!(f, "{}", token),
+            Node::Quote(prefix, datum) => write!(f, "{}{}", prefix, datum),
+            Node::List { open, items, tail, close } => {
+                write!(f, "{}", open)?;
+                for item in items {
+                    write!(f, "{}", item)?;
+                }
+                if let Some((dot, tail)) = tail {
+                    write!(f, "{}{}", dot, tail)?;
+                }
+                write!(f, "{}", close)
+            }
+            Node::Vector { open, items, close } => {
+                write!(f, "{}", open)?;
+                for item in items {
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "{}", close)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Root {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for datum in &self.datums {
+            write!(f, "{}", datum)?;
+        }
+        for trivia in &self.trailing {
+            write!(f, "{}", trivia)?;
+        }
+        Ok(())
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::cst::parse;
+
+    const SOURCE: &str = "; header\n\
+        (define (f x)   ; trailing\n  \
+        #| block #| nested |# |#\n  \
+        `(,x ,@(g 'y) . z)) #;(dropped 1)\n\
+        #( 1.50 #e1.5 |a b| \"s\\n\" #\\( ) \t\n";
+
+    #[test]
+    fn prints_the_source_back() {
+        for src in &[SOURCE, "", "  ", "a", "#;a", "(a . b)", "((a) . (b c))", "#;  #;a b c"] {
+            assert_eq!(parse(src).unwrap().to_string(), *src);
+        }
+    }
+
+    #[test]
+    fn trivia_goes_on_the_next_token() {
+        let root = parse("; c\n(a #;b\n)  ").unwrap();
+        let open = root.datums[0].first_token();
+        assert_eq!(open.leading, vec![
+            Trivia::LineComment("; c".to_owned()),
+            Trivia::Whitespace("\n".to_owned()),
+        ]);
+        match &root.datums[0] {
+            Node::List { close, .. } => assert_eq!(close.leading, vec![
+                Trivia::Whitespace(" ".to_owned()),
+                Trivia::DatumComment(Box::new(Node::Atom(Token::new(TokenKind::Atom, "b")))),
+                Trivia::Whitespace("\n".to_owned()),
+            ]),
+            other => panic!("not a list: {:?}", other),
+        }
+        assert_eq!(root.trailing, vec![Trivia::Whitespace("  ".to_owned())]);
+    }
+
+    #[test]
+    fn converts_to_sexps() {
+        assert_eq!(parse(SOURCE).unwrap().to_sexps(), crate::parse::parse_all(SOURCE));
+        assert_eq!(parse("(a . (b . c))").unwrap().to_sexps(), crate::parse::parse_all("(a b . c)"));
+    }
+
+    #[test]
+    fn edits_keep_the_layout() {
+        let mut root = parse("(let ((x 1)) ; x is one\n  (+ x #;x 2))").unwrap();
+        root.for_each_token_mut(|token| if token.text == "x" {
+            token.text = "count".to_owned();
+        });
+        assert_eq!(root.to_string(), "(let ((count 1)) ; x is one\n  (+ count #;x 2))");
+    }
+
+    #[test]
+    fn errors_are_the_parsers() {
+        for src in &["(a", "(a . b c)", "#|", ",1"] {
+            assert_eq!(parse(src), Err(crate::parse::parse_all(src).unwrap_err()));
+        }
+    }
+}
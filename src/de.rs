@@ -0,0 +1,451 @@
+/// Reading Rust values out of S-expressions with serde, in the forms that
+/// `ser` writes.
+///
+/// Errors point at the datum they're about, so a field of the wrong type is
+/// reported at that field's value.
+use std::{convert::TryFrom, fmt, slice};
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+
+use crate::{
+    expr::{Atom, Num, Quote, Sexp},
+    parse,
+    span::{LineCol, LineIndex, Span, Spanned, SpannedQuote, SpannedSexp},
+};
+
+/// A datum that doesn't fit the type being read, or source that doesn't parse.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Error {
+    message: String,
+    span: Option<Span>,
+    position: Option<LineCol>,
+}
+
+impl Error {
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The source of the datum the error is about, if it was read by `from_str`.
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// Where `span` starts.
+    pub fn position(&self) -> Option<LineCol> {
+        self.position
+    }
+
+    fn custom_at(msg: &str, span: Span) -> Error {
+        Error { message: msg.to_owned(), span: Some(span), position: None }
+    }
+
+    // Only the innermost datum gets to say where an error is.
+    fn or_span(mut self, span: Span) -> Error {
+        self.span.get_or_insert(span);
+        self
+    }
+
+    fn located(mut self, index: &LineIndex) -> Error {
+        self.position = self.span.map(|span| index.line_col(span.start));
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.position {
+            Some(LineCol { line, column }) => {
+                write!(f, "{} at line {}, column {}", self.message, line, column)
+            }
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error { message: msg.to_string(), span: None, position: None }
+    }
+}
+
+impl From<crate::Error> for Error {
+    fn from(e: crate::Error) -> Error {
+        Error {
+            message: e.kind().to_string(),
+            span: Some(Span::new(e.offset(), e.offset())),
+            position: Some(LineCol { line: e.line(), column: e.column() }),
+        }
+    }
+}
+
+/// Read a `T` from a source text holding exactly one datum.
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Error> {
+    let index = LineIndex::new(s);
+    let datums = parse::spanned::parse_all(s)?;
+    let datum = match datums.as_slice() {
+        [datum] => datum,
+        [] => {
+            let end = Span::new(s.len(), s.len());
+            return Err(Error::custom_at("expected a datum", end).located(&index));
+        }
+        [_, extra, ..] => {
+            return Err(Error::custom_at("expected the end of input", extra.span).located(&index));
+        }
+    };
+    T::deserialize(Node(datum)).map_err(|e| e.located(&index))
+}
+
+/// Read a `T` from an already parsed datum, whose errors have no position.
+pub fn from_sexp<T: DeserializeOwned>(sexp: &Sexp) -> Result<T, Error> {
+    T::deserialize(Node(&spanless(sexp))).map_err(|e| Error { span: None, ..e })
+}
+
+// `sexp` with every span empty, so both entry points share one deserializer.
+fn spanless(sexp: &Sexp) -> Spanned<SpannedSexp> {
+    let all = |items: &[Sexp]| items.iter().map(spanless).collect();
+    let quoted = |s: &Sexp| Box::new(spanless(s));
+    let node = match sexp {
+        Sexp::Quote(Quote::Quote(s)) => SpannedSexp::Quote(SpannedQuote::Quote(quoted(s))),
+        Sexp::Quote(Quote::Quasi(s)) => SpannedSexp::Quote(SpannedQuote::Quasi(quoted(s))),
+        Sexp::Quote(Quote::UnQuote(s)) => SpannedSexp::Quote(SpannedQuote::UnQuote(quoted(s))),
+        Sexp::Quote(Quote::Splice(s)) => SpannedSexp::Quote(SpannedQuote::Splice(quoted(s))),
+        Sexp::Constant(a) => SpannedSexp::Constant(a.clone()),
+        Sexp::List(items) => SpannedSexp::List(all(items)),
+        Sexp::DottedList(items, tail) => SpannedSexp::DottedList(all(items), quoted(tail)),
+        Sexp::Vector(items) => SpannedSexp::Vector(all(items)),
+    };
+    Spanned::new(node, Span::new(0, 0))
+}
+
+fn fail<T>(msg: &str) -> Result<T, Error> {
+    Err(de::Error::custom(msg))
+}
+
+// The name of a `:keyword`, or `None` for any other datum.
+fn keyword(sexp: &SpannedSexp) -> Option<&str> {
+    match sexp {
+        SpannedSexp::Constant(Atom::Symbol(s)) if s.len() > 1 => s.strip_prefix(':'),
+        _ => None,
+    }
+}
+
+fn is_nil(sexp: &SpannedSexp) -> bool {
+    matches!(sexp, SpannedSexp::Constant(Atom::Symbol(s)) if s == "nil")
+}
+
+// A keyword tagged list, `(:a 1 :b 2)`.
+fn is_keyword_list(items: &[Spanned<SpannedSexp>]) -> bool {
+    !items.is_empty() && items.len().is_multiple_of(2) && items.iter().step_by(2).all(|k| keyword(&k.node).is_some())
+}
+
+
+
+// Datums
+
+/// Deserializes one datum.
+struct Node<'a>(&'a Spanned<SpannedSexp>);
+
+impl<'a> Node<'a> {
+    fn any<'de, V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, Error> {
+        match &self.0.node {
+            SpannedSexp::Constant(atom) => match atom {
+                Atom::Num(Num::Int(i)) => visitor.visit_i64(*i),
+                Atom::Num(Num::Float(x)) => visitor.visit_f64(*x),
+                Atom::Num(Num::Ratio(r)) => visitor.visit_f64(r.numer() as f64 / r.denom() as f64),
+                // Only a `u64` holds more than `Int` does
+                #[cfg(feature = "bigint")]
+                Atom::Num(Num::BigInt(i)) => match u64::try_from(i) {
+                    Ok(u) => visitor.visit_u64(u),
+                    Err(_) => fail("integer is too large"),
+                },
+                Atom::Bool(b) => visitor.visit_bool(*b),
+                Atom::Char(c) => visitor.visit_char(*c),
+                Atom::Symbol(s) | Atom::String(s) => visitor.visit_str(s),
+            },
+            SpannedSexp::List(items) if is_keyword_list(items) => visitor.visit_map(Fields::new(items)),
+            SpannedSexp::List(items) | SpannedSexp::Vector(items) => visit_items(items, visitor),
+            SpannedSexp::DottedList(..) => fail("can't read a dotted list"),
+            SpannedSexp::Quote(_) => fail("can't read a quoted datum"),
+        }
+    }
+
+    fn items(&self) -> Result<&'a [Spanned<SpannedSexp>], Error> {
+        match &self.0.node {
+            SpannedSexp::List(items) | SpannedSexp::Vector(items) => Ok(items),
+            _ => fail("expected a list"),
+        }
+    }
+
+    fn option<'de, V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, Error> {
+        if is_nil(&self.0.node) {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(Node(self.0))
+        }
+    }
+
+    fn unit<'de, V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, Error> {
+        match &self.0.node {
+            SpannedSexp::List(items) if items.is_empty() => visitor.visit_unit(),
+            _ => fail("expected `()`"),
+        }
+    }
+
+    fn map<'de, V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(Pairs { items: self.items()?.iter(), value: None })
+    }
+
+    fn fields<'de, V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(Fields::new(self.items()?))
+    }
+
+    fn variant<'de, V: Visitor<'de>>(&self, visitor: V) -> Result<V::Value, Error> {
+        let (name, args) = match &self.0.node {
+            SpannedSexp::Constant(Atom::Symbol(_)) => (self.0, &[][..]),
+            SpannedSexp::List(items) => match items.split_first() {
+                Some((head @ Spanned { node: SpannedSexp::Constant(Atom::Symbol(_)), .. }, args)) => {
+                    (head, args)
+                }
+                _ => return fail("expected a list headed by a variant name"),
+            },
+            _ => return fail("expected a variant name, or a list headed by one"),
+        };
+        visitor.visit_enum(Variant { name, args })
+    }
+}
+
+// Hand `items` to `visitor` as a sequence, failing if it leaves any behind.
+fn visit_items<'de, V: Visitor<'de>>(items: &[Spanned<SpannedSexp>], visitor: V) -> Result<V::Value, Error> {
+    let mut seq = Items(items.iter());
+    let value = visitor.visit_seq(&mut seq)?;
+    match seq.0.next() {
+        Some(extra) => Err(Error::custom_at("too many items", extra.span)),
+        None => Ok(value),
+    }
+}
+
+// Every method puts its own span on the errors that don't have one yet.
+macro_rules! located {
+    ($($method:ident => $inner:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                self.$inner(visitor).map_err(|e| e.or_span(self.0.span))
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for Node<'a> {
+    type Error = Error;
+
+    located! {
+        deserialize_any => any,
+        deserialize_bool => any,
+        deserialize_i8 => any,
+        deserialize_i16 => any,
+        deserialize_i32 => any,
+        deserialize_i64 => any,
+        deserialize_u8 => any,
+        deserialize_u16 => any,
+        deserialize_u32 => any,
+        deserialize_u64 => any,
+        deserialize_f32 => any,
+        deserialize_f64 => any,
+        deserialize_char => any,
+        deserialize_str => any,
+        deserialize_string => any,
+        deserialize_bytes => any,
+        deserialize_byte_buf => any,
+        deserialize_identifier => any,
+        deserialize_option => option,
+        deserialize_unit => unit,
+        deserialize_map => map,
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        let span = self.0.span;
+        visitor.visit_newtype_struct(self).map_err(|e| e.or_span(span))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.items()
+            .and_then(|items| visit_items(items, visitor))
+            .map_err(|e| e.or_span(self.0.span))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.fields(visitor).map_err(|e| e.or_span(self.0.span))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.variant(visitor).map_err(|e| e.or_span(self.0.span))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+
+
+// Compound datums
+
+/// The items of a list or vector as a sequence.
+struct Items<'a>(slice::Iter<'a, Spanned<SpannedSexp>>);
+
+impl<'de, 'a> de::SeqAccess<'de> for Items<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        self.0.next().map(|item| seed.deserialize(Node(item))).transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+/// A keyword tagged list as a struct's fields.
+struct Fields<'a> {
+    items: slice::Iter<'a, Spanned<SpannedSexp>>,
+    value: Option<&'a Spanned<SpannedSexp>>,
+}
+
+impl<'a> Fields<'a> {
+    fn new(items: &'a [Spanned<SpannedSexp>]) -> Fields<'a> {
+        Fields { items: items.iter(), value: None }
+    }
+}
+
+impl<'de, 'a> de::MapAccess<'de> for Fields<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        let key = match self.items.next() {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        let name = keyword(&key.node)
+            .ok_or_else(|| Error::custom_at("expected a keyword like `:name`", key.span))?;
+        self.value = Some(self.items.next().ok_or_else(|| {
+            Error::custom_at(&format!("`:{}` has no value after it", name), key.span)
+        })?);
+        seed.deserialize(name.into_deserializer()).map(Some).map_err(|e: Error| e.or_span(key.span))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        seed.deserialize(Node(self.value.take().expect("next_key_seed comes first")))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len() / 2)
+    }
+}
+
+/// A list of `(key value)` pairs as a map.
+struct Pairs<'a> {
+    items: slice::Iter<'a, Spanned<SpannedSexp>>,
+    value: Option<&'a Spanned<SpannedSexp>>,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for Pairs<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        let pair = match self.items.next() {
+            Some(pair) => pair,
+            None => return Ok(None),
+        };
+        match &pair.node {
+            SpannedSexp::List(kv) if kv.len() == 2 => {
+                self.value = Some(&kv[1]);
+                seed.deserialize(Node(&kv[0])).map(Some)
+            }
+            _ => Err(Error::custom_at("expected a `(key value)` pair", pair.span)),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        seed.deserialize(Node(self.value.take().expect("next_key_seed comes first")))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+/// An enum variant, `name` alone or `(name args...)`.
+struct Variant<'a> {
+    name: &'a Spanned<SpannedSexp>,
+    args: &'a [Spanned<SpannedSexp>],
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for Variant<'a> {
+    type Error = Error;
+    type Variant = Variant<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Variant<'a>), Error> {
+        let value = seed.deserialize(Node(self.name)).map_err(|e| e.or_span(self.name.span))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for Variant<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.args.first() {
+            Some(extra) => Err(Error::custom_at("this variant takes nothing after its name", extra.span)),
+            None => Ok(()),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        match self.args {
+            [value] => seed.deserialize(Node(value)),
+            _ => fail("expected one datum after the variant name"),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        visit_items(self.args, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_map(Fields::new(self.args))
+    }
+}
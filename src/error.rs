@@ -0,0 +1,342 @@
+/// A parse error that doesn't expose the parser internals.
+use std::fmt;
+
+use nom::error::{VerboseError, VerboseErrorKind};
+
+use crate::span::{LineCol, LineIndex};
+
+// The labels the parsers hand to `nom::error::context`, or attach to their
+// own failures. Each names what was being parsed, to read after "while
+// parsing", and `ErrorKind::from_context` turns them back into kinds.
+pub(crate) const CLOSING_PAREN: &str = "list";
+pub(crate) const CLOSING_QUOTE: &str = "string";
+pub(crate) const CLOSING_BAR: &str = "|symbol|";
+pub(crate) const BAD_ESCAPE: &str = "string escape";
+pub(crate) const BLOCK_COMMENT: &str = "block comment";
+pub(crate) const BAD_CHAR_NAME: &str = "character name";
+pub(crate) const DOTTED_TAIL: &str = "dotted list tail";
+pub(crate) const INTEGER_OVERFLOW: &str = "integer literal";
+pub(crate) const MALFORMED_NUMBER: &str = "number";
+pub(crate) const INVALID_SYMBOL: &str = "symbol";
+pub(crate) const ZERO_DENOMINATOR: &str = "ratio denominator";
+pub(crate) const QUOTED_DATUM: &str = "quoted datum";
+pub(crate) const COMMENTED_DATUM: &str = "commented datum";
+pub(crate) const UNQUOTE_LITERAL: &str = "unquote";
+pub(crate) const SPLICE_LITERAL: &str = "unquote-splicing";
+pub(crate) const END_OF_INPUT: &str = "top-level datums";
+// Where the list a `CLOSING_PAREN` failure belongs to starts. It only moves the
+// error there, and stays out of the context stack.
+pub(crate) const OPENING_PAREN: &str = "opening paren";
+
+/// What went wrong.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ErrorKind {
+    /// A list or vector is missing its `)`.
+    UnclosedParen,
+    /// A string is missing its closing `"`.
+    UnterminatedString,
+    /// A `|symbol|` is missing its closing `|`.
+    UnterminatedSymbol,
+    /// A `\` in a string followed by something that isn't an escape.
+    BadEscape,
+    /// A `#|` comment is missing its `|#`.
+    UnterminatedBlockComment,
+    /// A `#\` character literal with a name we don't know.
+    BadCharName,
+    /// An integer literal that doesn't fit an `i64` without the `bigint` feature,
+    /// or a ratio or `#e` decimal whose parts don't fit one even with it.
+    IntegerOverflow,
+    /// A `.` anywhere but between the last two datums of a list.
+    MisplacedDot,
+    /// A number we can't read.
+    MalformedNumber,
+    /// A ratio literal like `1/0`.
+    DivisionByZero,
+    /// A token that isn't any other atom and has a `#` in it.
+    InvalidSymbol,
+    /// A quote prefix or `#;` with no datum after it.
+    MissingDatum,
+    /// `,` in front of a literal other than a symbol.
+    UnquoteLiteral,
+    /// `,@` in front of a literal other than a symbol.
+    SpliceLiteral,
+    /// Something other than a datum where a datum or the end was expected.
+    TrailingInput,
+    /// The input ran out in the middle of a datum.
+    UnexpectedEof,
+    /// The input goes past one of the `ParseOptions` limits.
+    LimitExceeded { limit: Limit, max: usize },
+    /// A `Reader`'s source failed.
+    Io(std::io::ErrorKind),
+    /// A `Reader`'s source isn't UTF-8.
+    InvalidUtf8,
+    /// Any other malformed input.
+    Syntax,
+}
+
+/// Which of the `ParseOptions` limits was exceeded.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Limit {
+    Depth,
+    AtomLength,
+    ListLength,
+    TotalNodes,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Limit::Depth => "nesting depth",
+            Limit::AtomLength => "atom length",
+            Limit::ListLength => "list length",
+            Limit::TotalNodes => "total datums",
+        })
+    }
+}
+
+impl ErrorKind {
+    fn from_context(label: &str) -> Option<ErrorKind> {
+        match label {
+            CLOSING_PAREN => Some(ErrorKind::UnclosedParen),
+            CLOSING_QUOTE => Some(ErrorKind::UnterminatedString),
+            CLOSING_BAR => Some(ErrorKind::UnterminatedSymbol),
+            BAD_ESCAPE => Some(ErrorKind::BadEscape),
+            BLOCK_COMMENT => Some(ErrorKind::UnterminatedBlockComment),
+            BAD_CHAR_NAME => Some(ErrorKind::BadCharName),
+            INTEGER_OVERFLOW => Some(ErrorKind::IntegerOverflow),
+            MALFORMED_NUMBER => Some(ErrorKind::MalformedNumber),
+            ZERO_DENOMINATOR => Some(ErrorKind::DivisionByZero),
+            INVALID_SYMBOL => Some(ErrorKind::InvalidSymbol),
+            DOTTED_TAIL => Some(ErrorKind::MisplacedDot),
+            QUOTED_DATUM | COMMENTED_DATUM => Some(ErrorKind::MissingDatum),
+            UNQUOTE_LITERAL => Some(ErrorKind::UnquoteLiteral),
+            SPLICE_LITERAL => Some(ErrorKind::SpliceLiteral),
+            END_OF_INPUT => Some(ErrorKind::TrailingInput),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ErrorKind::UnclosedParen => "unclosed paren, expected `)`",
+            ErrorKind::UnterminatedString => "unterminated string, expected `\"`",
+            ErrorKind::UnterminatedSymbol => "unterminated symbol, expected `|`",
+            ErrorKind::BadEscape => "unknown string escape",
+            ErrorKind::UnterminatedBlockComment => "unterminated block comment, expected `|#`",
+            ErrorKind::BadCharName => "unknown character name",
+            ErrorKind::IntegerOverflow => "integer literal is too large",
+            ErrorKind::MalformedNumber => "malformed number",
+            ErrorKind::DivisionByZero => "ratio with a zero denominator",
+            ErrorKind::InvalidSymbol => "invalid symbol",
+            ErrorKind::MisplacedDot => "misplaced `.`, it may only come before the last datum of a list",
+            ErrorKind::MissingDatum => "expected a datum",
+            ErrorKind::UnquoteLiteral => "can't unquote a literal",
+            ErrorKind::SpliceLiteral => "can't splice a literal",
+            ErrorKind::TrailingInput => "unexpected input, expected a datum or the end of input",
+            ErrorKind::UnexpectedEof => "unexpected end of input",
+            ErrorKind::LimitExceeded { limit, max } => {
+                return write!(f, "{} is over the limit of {}", limit, max)
+            }
+            ErrorKind::Io(kind) => return write!(f, "read failed: {}", kind),
+            ErrorKind::InvalidUtf8 => "input is not valid UTF-8",
+            ErrorKind::Syntax => "invalid syntax",
+        })
+    }
+}
+
+/// A parse error with its position in the source and the context stack the
+/// parsers recorded on the way out, innermost first.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Error {
+    kind: ErrorKind,
+    offset: usize,
+    position: LineCol,
+    context: Vec<&'static str>,
+    // The source line the error is on, for rendering
+    line_text: String,
+}
+
+impl Error {
+    /// Build an error from what a parser returned while reading `source`.
+    ///
+    /// Every slice in `err` must be a suffix of `source`, as it is for any
+    /// parser in `rexp::parse` that was handed `source`.
+    pub fn from_verbose(source: &str, err: VerboseError<&str>) -> Error {
+        let context: Vec<&'static str> = err
+            .errors
+            .iter()
+            .filter_map(|(_, kind)| match kind {
+                VerboseErrorKind::Context(OPENING_PAREN) => None,
+                VerboseErrorKind::Context(label) => Some(*label),
+                _ => None,
+            })
+            .collect();
+        let opened = err.errors.iter().find_map(|(open, kind)| match kind {
+            VerboseErrorKind::Context(OPENING_PAREN) => Some(*open),
+            _ => None,
+        });
+        let rest = err.errors.first().map_or("", |(rest, _)| *rest);
+        let kind = context
+            .iter()
+            .find_map(|label| ErrorKind::from_context(label))
+            .unwrap_or(if rest.is_empty() {
+                ErrorKind::UnexpectedEof
+            } else {
+                ErrorKind::Syntax
+            });
+        // An unclosed list is pointed at by its innermost `(`
+        let at = match opened {
+            Some(open) if kind == ErrorKind::UnclosedParen => open,
+            _ => rest,
+        };
+        let mut error = Error::at(source, source.len() - at.len(), kind);
+        error.context = context;
+        error
+    }
+
+    /// Build an error from a failed parser run over the whole of `source`.
+    pub fn from_nom(source: &str, err: nom::Err<VerboseError<&str>>) -> Error {
+        match err {
+            nom::Err::Error(e) | nom::Err::Failure(e) => Error::from_verbose(source, e),
+            nom::Err::Incomplete(_) => Error::at(source, source.len(), ErrorKind::UnexpectedEof),
+        }
+    }
+
+    pub(crate) fn at(source: &str, offset: usize, kind: ErrorKind) -> Error {
+        let index = LineIndex::new(source);
+        let position = index.line_col(offset);
+        Error {
+            kind,
+            offset,
+            position,
+            context: vec![],
+            line_text: index.line_text(position.line).unwrap_or("").to_owned(),
+        }
+    }
+
+    // Move an error in `source` to where `source` sits in a longer text: after
+    // `offset` bytes, starting on `line`, and with `line_start` before it on
+    // that line.
+    pub(crate) fn shifted(mut self, offset: usize, line: usize, line_start: &str) -> Error {
+        self.offset += offset;
+        if self.position.line == 1 {
+            self.position.column += line_start.chars().count();
+            self.line_text.insert_str(0, line_start);
+        }
+        self.position.line += line - 1;
+        self
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Byte offset into the source.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// 1 based line.
+    pub fn line(&self) -> usize {
+        self.position.line
+    }
+
+    /// 1 based column, counted in `char`s.
+    pub fn column(&self) -> usize {
+        self.position.column
+    }
+
+    /// The `error::context` labels from the innermost parser outwards.
+    pub fn context(&self) -> &[&'static str] {
+        &self.context
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line = self.line().to_string();
+        let gutter = " ".repeat(line.len());
+        // Keep tabs so the caret lines up with the source above it
+        let pad: String = self
+            .line_text
+            .chars()
+            .take(self.column() - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        writeln!(f, "error: {}", self.kind)?;
+        writeln!(f, "{}--> {}:{}", gutter, self.line(), self.column())?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", line, self.line_text)?;
+        write!(f, "{} | {}^", gutter, pad)?;
+        for label in &self.context {
+            write!(f, "\n{} = while parsing {}", gutter, label)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn error(src: &str) -> Error {
+        parse::parse_all(src).unwrap_err()
+    }
+
+    #[test]
+    fn kinds_from_context() {
+        assert_eq!(error("(a (b c)").kind(), &ErrorKind::UnclosedParen);
+        assert_eq!(error("(a \"b c)").kind(), &ErrorKind::UnterminatedString);
+        assert_eq!(error("(a |b c)").kind(), &ErrorKind::UnterminatedSymbol);
+        assert_eq!(error("(a \"\\d\")").kind(), &ErrorKind::BadEscape);
+        assert_eq!(error("#| a").kind(), &ErrorKind::UnterminatedBlockComment);
+        assert_eq!(error("(#\\nope)").kind(), &ErrorKind::BadCharName);
+        #[cfg(not(feature = "bigint"))]
+        assert_eq!(error("(1 99999999999999999999)").kind(), &ErrorKind::IntegerOverflow);
+        assert_eq!(error("(a . b c)").kind(), &ErrorKind::MisplacedDot);
+        assert_eq!(error("(a ')").kind(), &ErrorKind::MissingDatum);
+        assert_eq!(error("#;").kind(), &ErrorKind::MissingDatum);
+        assert_eq!(error(",5").kind(), &ErrorKind::UnquoteLiteral);
+        assert_eq!(error(",@\"s\"").kind(), &ErrorKind::SpliceLiteral);
+        assert_eq!(error("a ) b").kind(), &ErrorKind::TrailingInput);
+    }
+
+    #[test]
+    fn positions() {
+        let err = error("(a\n  (b c)\n  ) )");
+        assert_eq!((err.offset(), err.line(), err.column()), (15, 3, 5));
+        let err = error("(a\n  (b c\n");
+        assert_eq!((err.offset(), err.line(), err.column()), (5, 2, 3));
+        let err = error("(define (f x)\n  (let ((y 1))\n    (g y)\n");
+        assert_eq!((err.line(), err.column()), (2, 3));
+        assert_eq!(err.line_text, "  (let ((y 1))");
+    }
+
+    #[test]
+    fn context_stack() {
+        let err = error("(a #; (b");
+        assert_eq!(err.context(), &[CLOSING_PAREN, COMMENTED_DATUM]);
+        assert!(err.to_string().ends_with("= while parsing list\n  = while parsing commented datum"));
+        assert!(error(",5").to_string().ends_with("= while parsing unquote"));
+    }
+
+    #[test]
+    fn render_snippet() {
+        assert_eq!(
+            error("(define x\n\t(f \"oops))\n").to_string(),
+            "error: unterminated string, expected `\"`\n \
+             --> 2:5\n  \
+             |\n\
+             2 | \t(f \"oops))\n  \
+             | \t   ^\n  \
+             = while parsing string"
+        );
+    }
+}
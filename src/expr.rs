@@ -1,13 +1,66 @@
 /// Symbolic Expression Types.
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    hash::{BuildHasher, Hash},
+    iter::FromIterator,
+    str::FromStr,
+};
+
+use crate::Error;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Num {
     Int(i64),
     Float(f64),
+    Ratio(Ratio),
+    /// Only for integers that don't fit in `Int`.
+    #[cfg(feature = "bigint")]
+    BigInt(num_bigint::BigInt),
+}
+
+/// An exact fraction in lowest terms with a positive denominator, so equal
+/// ratios always compare equal.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Ratio {
+    numer: i64,
+    denom: i64,
+}
+
+impl Ratio {
+    /// `None` if `denom` is zero, or the normalized ratio doesn't fit.
+    pub fn new(numer: i64, denom: i64) -> Option<Ratio> {
+        use std::convert::TryFrom;
+        if denom == 0 {
+            return None;
+        }
+        let (mut a, mut b) = (numer.unsigned_abs(), denom.unsigned_abs());
+        while b != 0 {
+            let r = a % b;
+            a = b;
+            b = r;
+        }
+        // Widen so `i64::MIN` can be divided and negated without overflowing
+        let gcd = a as i128 * (denom as i128).signum();
+        Some(Ratio {
+            numer: i64::try_from(numer as i128 / gcd).ok()?,
+            denom: i64::try_from(denom as i128 / gcd).ok()?,
+        })
+    }
+
+    pub fn numer(&self) -> i64 {
+        self.numer
+    }
+
+    pub fn denom(&self) -> i64 {
+        self.denom
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Atom {
     Num(Num),
+    Bool(bool),
     Char(char),
     Symbol(String),
     String(String),
@@ -26,5 +79,593 @@ pub enum Sexp {
     Quote(Quote),
     Constant(Atom),
     List(Vec<Sexp>),
+    /// An improper list like `(a b . c)`, whose tail is never itself a list.
+    DottedList(Vec<Sexp>, Box<Sexp>),
     Vector(Vec<Sexp>),
 }
+
+
+
+// Printing
+//
+// Everything prints in a form the reader turns back into an equal value.
+
+impl fmt::Display for Num {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Num::Int(i) => write!(f, "{}", i),
+            Num::Ratio(r) => write!(f, "{}", r),
+            #[cfg(feature = "bigint")]
+            Num::BigInt(i) => write!(f, "{}", i),
+            Num::Float(x) if x.is_nan() => f.write_str("+nan.0"),
+            Num::Float(x) if x.is_infinite() => {
+                f.write_str(if *x > 0.0 { "+inf.0" } else { "-inf.0" })
+            }
+            // `Debug` is the shortest form that reads back exactly, and
+            // always has a `.` or an exponent
+            Num::Float(x) => write!(f, "{:?}", x),
+        }
+    }
+}
+
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.numer, self.denom)
+    }
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Atom::Num(n) => write!(f, "{}", n),
+            Atom::Bool(b) => f.write_str(if *b { "#t" } else { "#f" }),
+            Atom::Char(c) => write_char(f, *c),
+            Atom::Symbol(s) => write_symbol(f, s),
+            Atom::String(s) => {
+                f.write_str("\"")?;
+                write_escaped(f, s, '"')?;
+                f.write_str("\"")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Quote {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Quote::Quote(s) => write!(f, "'{}", s),
+            Quote::Quasi(s) => write!(f, "`{}", s),
+            // `,@foo` would read back as a splice
+            Quote::UnQuote(s) => match &**s {
+                Sexp::Constant(Atom::Symbol(name)) if name.starts_with('@') => {
+                    f.write_str(",|")?;
+                    write_escaped(f, name, '|')?;
+                    f.write_str("|")
+                }
+                s => write!(f, ",{}", s),
+            },
+            Quote::Splice(s) => write!(f, ",@{}", s),
+        }
+    }
+}
+
+impl fmt::Display for Sexp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn write_items(f: &mut fmt::Formatter, items: &[Sexp]) -> fmt::Result {
+            for (n, item) in items.iter().enumerate() {
+                if n > 0 {
+                    f.write_str(" ")?;
+                }
+                write!(f, "{}", item)?;
+            }
+            Ok(())
+        }
+        match self {
+            Sexp::Quote(q) => write!(f, "{}", q),
+            Sexp::Constant(a) => write!(f, "{}", a),
+            Sexp::List(items) => {
+                f.write_str("(")?;
+                write_items(f, items)?;
+                f.write_str(")")
+            }
+            // `( . x)` is just `x`
+            Sexp::DottedList(items, tail) if items.is_empty() => write!(f, "{}", tail),
+            Sexp::DottedList(items, tail) => {
+                f.write_str("(")?;
+                write_items(f, items)?;
+                write!(f, " . {})", tail)
+            }
+            Sexp::Vector(items) => {
+                f.write_str("#(")?;
+                write_items(f, items)?;
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+fn write_char(f: &mut fmt::Formatter, c: char) -> fmt::Result {
+    let name = match c {
+        '\x00' => "nul",
+        '\x07' => "alarm",
+        '\x08' => "backspace",
+        '\t'   => "tab",
+        '\n'   => "newline",
+        '\x0B' => "vtab",
+        '\x0C' => "page",
+        '\r'   => "return",
+        '\x1B' => "escape",
+        ' '    => "space",
+        '\x7F' => "delete",
+        c if c.is_control() || c.is_whitespace() => return write!(f, "#\\x{:x}", c as u32),
+        c => return write!(f, "#\\{}", c),
+    };
+    write!(f, "#\\{}", name)
+}
+
+// Symbols go between bars unless they read back as themselves without them.
+// A leading bar only reads back when no later bar closes it, so it always
+// gets bars.
+fn write_symbol(f: &mut fmt::Formatter, name: &str) -> fmt::Result {
+    match crate::parse::sexp(name) {
+        Ok(("", Sexp::Constant(Atom::Symbol(ref read))))
+            if read == name && !name.starts_with('|') => f.write_str(name),
+        _ => {
+            f.write_str("|")?;
+            write_escaped(f, name, '|')?;
+            f.write_str("|")
+        }
+    }
+}
+
+// The body of a string or `|symbol|` closed by `end`.
+fn write_escaped(f: &mut fmt::Formatter, s: &str, end: char) -> fmt::Result {
+    for c in s.chars() {
+        match c {
+            '\\'   => f.write_str("\\\\")?,
+            '\x07' => f.write_str("\\a")?,
+            '\x08' => f.write_str("\\b")?,
+            '\t'   => f.write_str("\\t")?,
+            '\n'   => f.write_str("\\n")?,
+            '\r'   => f.write_str("\\r")?,
+            c if c == end => write!(f, "\\{}", c)?,
+            c if c.is_control() => write!(f, "\\x{:x};", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    Ok(())
+}
+
+
+
+// Reading
+
+impl FromStr for Sexp {
+    type Err = Error;
+
+    /// Read a string holding exactly one datum.
+    fn from_str(s: &str) -> Result<Sexp, Error> {
+        crate::parse::parse(s)
+    }
+}
+
+
+
+// Converting
+//
+// `ToSexp` and `FromSexp` turn Rust values into datums and back, in the same
+// forms `rexp::ser` uses: `None` is `nil`, sequences and tuples are lists, and
+// maps are lists of `(key value)` pairs. `#[derive(ToSexp, FromSexp)]` from
+// `rexp-derive` writes them for structs and enums.
+
+#[cfg(feature = "derive")]
+pub use rexp_derive::{FromSexp, ToSexp};
+
+pub trait ToSexp {
+    fn to_sexp(&self) -> Sexp;
+}
+
+pub trait FromSexp: Sized {
+    fn from_sexp(sexp: &Sexp) -> Result<Self, FromSexpError>;
+}
+
+/// A datum that isn't the shape a `FromSexp` impl wants.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FromSexpError {
+    message: String,
+}
+
+impl FromSexpError {
+    pub fn new(message: impl Into<String>) -> FromSexpError {
+        FromSexpError { message: message.into() }
+    }
+
+    /// "expected `what`, found `found`"
+    pub fn expected(what: &str, found: &Sexp) -> FromSexpError {
+        FromSexpError::new(format!("expected {}, found {}", what, found))
+    }
+
+    pub fn missing(field: &str) -> FromSexpError {
+        FromSexpError::new(format!("missing field `{}`", field))
+    }
+
+    /// The same error, said to be in `field`.
+    pub fn within(self, field: &str) -> FromSexpError {
+        FromSexpError::new(format!("in `{}`, {}", field, self.message))
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for FromSexpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for FromSexpError {}
+
+/// The items of a list read as positional arguments followed by
+/// `:keyword value` pairs, like `(point 1 2 :label "a")`.
+///
+/// Positional arguments stop early at the first keyword, so a trailing
+/// positional argument can be left out.
+#[derive(Debug, Clone)]
+pub struct Form<'a> {
+    positional: &'a [Sexp],
+    keywords: Vec<(&'a str, &'a Sexp)>,
+}
+
+impl<'a> Form<'a> {
+    /// Up to `positional` arguments, then pairs whose keywords, without their
+    /// `:`, are all in `keywords`.
+    pub fn new(items: &'a [Sexp], positional: usize, keywords: &[&str]) -> Result<Form<'a>, FromSexpError> {
+        let n = items.iter().take(positional).take_while(|item| keyword(item).is_none()).count();
+        let (positional_items, rest) = items.split_at(n);
+        let mut pairs = Vec::new();
+        let mut rest = rest.iter();
+        while let Some(key) = rest.next() {
+            let name = match keyword(key) {
+                Some(name) => name,
+                None if keywords.is_empty() => {
+                    return Err(FromSexpError::new(format!("too many items, expected at most {}", positional)))
+                }
+                None => return Err(FromSexpError::expected("a keyword like `:name`", key)),
+            };
+            if !keywords.contains(&name) {
+                let known = keywords.iter().map(|k| format!("`:{}`", k)).collect::<Vec<_>>();
+                let expected = match known.len() {
+                    0 => "no keywords".to_owned(),
+                    _ => format!("one of {}", known.join(", ")),
+                };
+                return Err(FromSexpError::new(format!("unknown field `:{}`, expected {}", name, expected)));
+            }
+            if pairs.iter().any(|&(seen, _)| seen == name) {
+                return Err(FromSexpError::new(format!("`:{}` is given twice", name)));
+            }
+            match rest.next() {
+                Some(value) => pairs.push((name, value)),
+                None => return Err(FromSexpError::new(format!("`:{}` has no value", name))),
+            }
+        }
+        Ok(Form { positional: positional_items, keywords: pairs })
+    }
+
+    /// `Form::new` on the items of a list.
+    pub fn of(sexp: &'a Sexp, positional: usize, keywords: &[&str]) -> Result<Form<'a>, FromSexpError> {
+        match sexp {
+            Sexp::List(items) => Form::new(items, positional, keywords),
+            _ => Err(FromSexpError::expected("a list", sexp)),
+        }
+    }
+
+    /// The name and arguments of `name` or `(name args...)`.
+    pub fn tagged(sexp: &'a Sexp) -> Result<(&'a str, &'a [Sexp]), FromSexpError> {
+        match sexp {
+            Sexp::Constant(Atom::Symbol(name)) => Ok((name, &[])),
+            Sexp::List(items) => match items.split_first() {
+                Some((Sexp::Constant(Atom::Symbol(name)), args)) => Ok((name, args)),
+                _ => Err(FromSexpError::expected("a list headed by a symbol", sexp)),
+            },
+            _ => Err(FromSexpError::expected("a symbol or a list headed by one", sexp)),
+        }
+    }
+
+    /// The `n`th positional argument.
+    pub fn positional(&self, n: usize) -> Option<&'a Sexp> {
+        self.positional.get(n)
+    }
+
+    /// The value after `:name`.
+    pub fn keyword(&self, name: &str) -> Option<&'a Sexp> {
+        self.keywords.iter().find(|&&(key, _)| key == name).map(|&(_, value)| value)
+    }
+}
+
+// The name of a `:keyword`, without its `:`.
+fn keyword(sexp: &Sexp) -> Option<&str> {
+    match sexp {
+        Sexp::Constant(Atom::Symbol(name)) if name.len() > 1 => name.strip_prefix(':'),
+        _ => None,
+    }
+}
+
+fn nil() -> Sexp {
+    Sexp::Constant(Atom::Symbol("nil".to_owned()))
+}
+
+impl ToSexp for Sexp {
+    fn to_sexp(&self) -> Sexp {
+        self.clone()
+    }
+}
+
+impl FromSexp for Sexp {
+    fn from_sexp(sexp: &Sexp) -> Result<Sexp, FromSexpError> {
+        Ok(sexp.clone())
+    }
+}
+
+impl<T: ToSexp + ?Sized> ToSexp for &T {
+    fn to_sexp(&self) -> Sexp {
+        (**self).to_sexp()
+    }
+}
+
+impl<T: ToSexp + ?Sized> ToSexp for Box<T> {
+    fn to_sexp(&self) -> Sexp {
+        (**self).to_sexp()
+    }
+}
+
+impl<T: FromSexp> FromSexp for Box<T> {
+    fn from_sexp(sexp: &Sexp) -> Result<Box<T>, FromSexpError> {
+        T::from_sexp(sexp).map(Box::new)
+    }
+}
+
+impl ToSexp for bool {
+    fn to_sexp(&self) -> Sexp {
+        Sexp::Constant(Atom::Bool(*self))
+    }
+}
+
+impl FromSexp for bool {
+    fn from_sexp(sexp: &Sexp) -> Result<bool, FromSexpError> {
+        match sexp {
+            Sexp::Constant(Atom::Bool(b)) => Ok(*b),
+            _ => Err(FromSexpError::expected("a boolean", sexp)),
+        }
+    }
+}
+
+impl ToSexp for char {
+    fn to_sexp(&self) -> Sexp {
+        Sexp::Constant(Atom::Char(*self))
+    }
+}
+
+impl FromSexp for char {
+    fn from_sexp(sexp: &Sexp) -> Result<char, FromSexpError> {
+        match sexp {
+            Sexp::Constant(Atom::Char(c)) => Ok(*c),
+            _ => Err(FromSexpError::expected("a character", sexp)),
+        }
+    }
+}
+
+impl ToSexp for str {
+    fn to_sexp(&self) -> Sexp {
+        Sexp::Constant(Atom::String(self.to_owned()))
+    }
+}
+
+impl ToSexp for String {
+    fn to_sexp(&self) -> Sexp {
+        self.as_str().to_sexp()
+    }
+}
+
+/// Symbols read as their names too.
+impl FromSexp for String {
+    fn from_sexp(sexp: &Sexp) -> Result<String, FromSexpError> {
+        match sexp {
+            Sexp::Constant(Atom::String(s)) | Sexp::Constant(Atom::Symbol(s)) => Ok(s.clone()),
+            _ => Err(FromSexpError::expected("a string", sexp)),
+        }
+    }
+}
+
+macro_rules! small_ints {
+    ($($t:ty),*) => {$(
+        impl ToSexp for $t {
+            fn to_sexp(&self) -> Sexp {
+                Sexp::Constant(Atom::Num(Num::Int(i64::from(*self))))
+            }
+        }
+    )*};
+}
+
+small_ints!(i8, i16, i32, i64, u8, u16, u32);
+
+impl ToSexp for isize {
+    fn to_sexp(&self) -> Sexp {
+        (*self as i64).to_sexp()
+    }
+}
+
+macro_rules! big_ints {
+    ($($t:ty),*) => {$(
+        /// Values too large for an `i64` are written as `Num::BigInt`, or
+        /// without the `bigint` feature as the nearest `Num::Float`, which
+        /// doesn't read back as an integer.
+        impl ToSexp for $t {
+            fn to_sexp(&self) -> Sexp {
+                use std::convert::TryFrom;
+                match i64::try_from(*self) {
+                    Ok(i) => i.to_sexp(),
+                    #[cfg(feature = "bigint")]
+                    Err(_) => Sexp::Constant(Atom::Num(Num::BigInt((*self).into()))),
+                    #[cfg(not(feature = "bigint"))]
+                    Err(_) => Sexp::Constant(Atom::Num(Num::Float(*self as f64))),
+                }
+            }
+        }
+    )*};
+}
+
+big_ints!(u64, usize);
+
+macro_rules! ints {
+    ($($t:ty),*) => {$(
+        impl FromSexp for $t {
+            fn from_sexp(sexp: &Sexp) -> Result<$t, FromSexpError> {
+                use std::convert::TryFrom;
+                let n = match sexp {
+                    Sexp::Constant(Atom::Num(Num::Int(i))) => <$t>::try_from(*i).ok(),
+                    #[cfg(feature = "bigint")]
+                    Sexp::Constant(Atom::Num(Num::BigInt(i))) => <$t>::try_from(i).ok(),
+                    _ => return Err(FromSexpError::expected("an integer", sexp)),
+                };
+                n.ok_or_else(|| FromSexpError::new(format!("{} doesn't fit in {}", sexp, stringify!($t))))
+            }
+        }
+    )*};
+}
+
+ints!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl ToSexp for f64 {
+    fn to_sexp(&self) -> Sexp {
+        Sexp::Constant(Atom::Num(Num::Float(*self)))
+    }
+}
+
+impl ToSexp for f32 {
+    fn to_sexp(&self) -> Sexp {
+        f64::from(*self).to_sexp()
+    }
+}
+
+/// Any number reads as a float.
+impl FromSexp for f64 {
+    fn from_sexp(sexp: &Sexp) -> Result<f64, FromSexpError> {
+        match sexp {
+            Sexp::Constant(Atom::Num(Num::Int(i))) => Ok(*i as f64),
+            Sexp::Constant(Atom::Num(Num::Float(x))) => Ok(*x),
+            Sexp::Constant(Atom::Num(Num::Ratio(r))) => Ok(r.numer() as f64 / r.denom() as f64),
+            #[cfg(feature = "bigint")]
+            Sexp::Constant(Atom::Num(Num::BigInt(i))) => Ok(i.to_string().parse().unwrap_or(f64::NAN)),
+            _ => Err(FromSexpError::expected("a number", sexp)),
+        }
+    }
+}
+
+impl FromSexp for f32 {
+    fn from_sexp(sexp: &Sexp) -> Result<f32, FromSexpError> {
+        f64::from_sexp(sexp).map(|x| x as f32)
+    }
+}
+
+impl<T: ToSexp> ToSexp for Option<T> {
+    fn to_sexp(&self) -> Sexp {
+        match self {
+            Some(value) => value.to_sexp(),
+            None => nil(),
+        }
+    }
+}
+
+impl<T: FromSexp> FromSexp for Option<T> {
+    fn from_sexp(sexp: &Sexp) -> Result<Option<T>, FromSexpError> {
+        if *sexp == nil() {
+            Ok(None)
+        } else {
+            T::from_sexp(sexp).map(Some)
+        }
+    }
+}
+
+impl<T: ToSexp> ToSexp for [T] {
+    fn to_sexp(&self) -> Sexp {
+        Sexp::List(self.iter().map(ToSexp::to_sexp).collect())
+    }
+}
+
+impl<T: ToSexp> ToSexp for Vec<T> {
+    fn to_sexp(&self) -> Sexp {
+        self.as_slice().to_sexp()
+    }
+}
+
+/// Vectors read as lists too.
+impl<T: FromSexp> FromSexp for Vec<T> {
+    fn from_sexp(sexp: &Sexp) -> Result<Vec<T>, FromSexpError> {
+        match sexp {
+            Sexp::List(items) | Sexp::Vector(items) => items.iter().map(T::from_sexp).collect(),
+            _ => Err(FromSexpError::expected("a list", sexp)),
+        }
+    }
+}
+
+macro_rules! tuple {
+    ($len:tt; $($name:ident $n:tt),*) => {
+        impl<$($name: ToSexp),*> ToSexp for ($($name,)*) {
+            fn to_sexp(&self) -> Sexp {
+                Sexp::List(vec![$(self.$n.to_sexp()),*])
+            }
+        }
+
+        impl<$($name: FromSexp),*> FromSexp for ($($name,)*) {
+            fn from_sexp(sexp: &Sexp) -> Result<Self, FromSexpError> {
+                match sexp {
+                    Sexp::List(items) if items.len() == $len => Ok(($($name::from_sexp(&items[$n])?,)*)),
+                    _ => Err(FromSexpError::expected(concat!("a list of length ", $len), sexp)),
+                }
+            }
+        }
+    };
+}
+
+tuple!(0;);
+tuple!(1; A 0);
+tuple!(2; A 0, B 1);
+tuple!(3; A 0, B 1, C 2);
+tuple!(4; A 0, B 1, C 2, D 3);
+tuple!(5; A 0, B 1, C 2, D 3, E 4);
+tuple!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+
+// The `(key value)` pairs of a map.
+fn pairs<'a, K: ToSexp + 'a, V: ToSexp + 'a>(entries: impl Iterator<Item = (&'a K, &'a V)>) -> Sexp {
+    Sexp::List(entries.map(|(k, v)| Sexp::List(vec![k.to_sexp(), v.to_sexp()])).collect())
+}
+
+fn from_pairs<K: FromSexp, V: FromSexp, M: FromIterator<(K, V)>>(sexp: &Sexp) -> Result<M, FromSexpError> {
+    Vec::<(K, V)>::from_sexp(sexp).map(|entries| entries.into_iter().collect())
+}
+
+impl<K: ToSexp, V: ToSexp, S> ToSexp for HashMap<K, V, S> {
+    fn to_sexp(&self) -> Sexp {
+        pairs(self.iter())
+    }
+}
+
+impl<K: FromSexp + Eq + Hash, V: FromSexp, S: BuildHasher + Default> FromSexp for HashMap<K, V, S> {
+    fn from_sexp(sexp: &Sexp) -> Result<HashMap<K, V, S>, FromSexpError> {
+        from_pairs(sexp)
+    }
+}
+
+impl<K: ToSexp, V: ToSexp> ToSexp for BTreeMap<K, V> {
+    fn to_sexp(&self) -> Sexp {
+        pairs(self.iter())
+    }
+}
+
+impl<K: FromSexp + Ord, V: FromSexp> FromSexp for BTreeMap<K, V> {
+    fn from_sexp(sexp: &Sexp) -> Result<BTreeMap<K, V>, FromSexpError> {
+        from_pairs(sexp)
+    }
+}
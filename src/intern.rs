@@ -0,0 +1,200 @@
+/// Interned variant of the Symbolic Expression types.
+///
+/// Symbols are `Copy` ids into a `SymbolTable`, so comparing two of them is
+/// an integer compare. Reuse one table for every file in a project and the
+/// same name gets the same `Symbol` everywhere.
+use std::collections::HashMap;
+
+use crate::expr::{self, Num};
+
+/// An interned symbol name, only meaningful with the table that made it.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Its position in the table, counting from 0 in the order names were interned.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// The names behind each `Symbol`.
+#[derive(Debug, Default, Clone)]
+pub struct SymbolTable {
+    names: Vec<Box<str>>,
+    ids: HashMap<Box<str>, Symbol>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable::default()
+    }
+
+    /// The `Symbol` for `name`, adding it if this is the first time it's seen.
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = Symbol(self.names.len() as u32);
+        self.names.push(name.into());
+        self.ids.insert(name.into(), id);
+        id
+    }
+
+    /// The `Symbol` for `name` if it has been interned.
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.ids.get(name).copied()
+    }
+
+    /// The name behind `symbol`.
+    ///
+    /// Panics if `symbol` came from another table that has more names.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// Another name for `SymbolTable`.
+pub type Interner = SymbolTable;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Atom {
+    Num(Num),
+    Bool(bool),
+    Char(char),
+    Symbol(Symbol),
+    String(String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Quote {
+    Quote(Box<Sexp>),
+    Quasi(Box<Sexp>),
+    UnQuote(Box<Sexp>),
+    Splice(Box<Sexp>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Sexp {
+    Quote(Quote),
+    Constant(Atom),
+    List(Vec<Sexp>),
+    /// The tail is never a `List` or `DottedList` itself.
+    DottedList(Vec<Sexp>, Box<Sexp>),
+    Vector(Vec<Sexp>),
+}
+
+// Looking the names back up gives the plain tree.
+
+impl Atom {
+    pub fn resolve(self, table: &SymbolTable) -> expr::Atom {
+        match self {
+            Atom::Num(n) => expr::Atom::Num(n),
+            Atom::Bool(b) => expr::Atom::Bool(b),
+            Atom::Char(c) => expr::Atom::Char(c),
+            Atom::Symbol(s) => expr::Atom::Symbol(table.resolve(s).to_owned()),
+            Atom::String(s) => expr::Atom::String(s),
+        }
+    }
+}
+
+impl Quote {
+    pub fn resolve(self, table: &SymbolTable) -> expr::Quote {
+        let resolve = |s: Box<Sexp>| Box::new(s.resolve(table));
+        match self {
+            Quote::Quote(s) => expr::Quote::Quote(resolve(s)),
+            Quote::Quasi(s) => expr::Quote::Quasi(resolve(s)),
+            Quote::UnQuote(s) => expr::Quote::UnQuote(resolve(s)),
+            Quote::Splice(s) => expr::Quote::Splice(resolve(s)),
+        }
+    }
+}
+
+impl Sexp {
+    /// The same tree as an `expr::Sexp`, with each `Symbol` swapped for its name.
+    pub fn resolve(self, table: &SymbolTable) -> expr::Sexp {
+        let resolve = |items: Vec<Sexp>| -> Vec<expr::Sexp> {
+            items.into_iter().map(|s| s.resolve(table)).collect()
+        };
+        match self {
+            Sexp::Quote(q) => expr::Sexp::Quote(q.resolve(table)),
+            Sexp::Constant(a) => expr::Sexp::Constant(a.resolve(table)),
+            Sexp::List(items) => expr::Sexp::List(resolve(items)),
+            Sexp::DottedList(items, tail) => {
+                expr::Sexp::DottedList(resolve(items), Box::new(tail.resolve(table)))
+            }
+            Sexp::Vector(items) => expr::Sexp::Vector(resolve(items)),
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::interned::{parse, parse_all};
+
+    #[test]
+    fn interning_is_stable() {
+        let mut table = SymbolTable::new();
+        let a = table.intern("a");
+        let b = table.intern("b");
+        assert_ne!(a, b);
+        assert_eq!(table.intern("a"), a);
+        assert_eq!(table.get("b"), Some(b));
+        assert_eq!(table.get("c"), None);
+        assert_eq!((table.resolve(a), table.resolve(b)), ("a", "b"));
+        assert_eq!(table.len(), 2);
+    }
+
+    #[test]
+    fn symbols_are_shared_across_files() {
+        let mut table = SymbolTable::new();
+        let first = parse_all("(define (f x) x)", &mut table).unwrap();
+        let second = parse("(f 'x)", &mut table).unwrap();
+        let f = table.get("f").unwrap();
+        let x = table.get("x").unwrap();
+        assert!(matches!(&first[0], Sexp::List(items) if items[1] == Sexp::List(vec![
+            Sexp::Constant(Atom::Symbol(f)),
+            Sexp::Constant(Atom::Symbol(x)),
+        ])));
+        assert_eq!(
+            second,
+            Sexp::List(vec![
+                Sexp::Constant(Atom::Symbol(f)),
+                Sexp::Quote(Quote::Quote(Box::new(Sexp::Constant(Atom::Symbol(x))))),
+            ])
+        );
+        assert_eq!(table.len(), 3);
+    }
+
+    #[test]
+    fn strings_are_not_interned() {
+        let mut table = SymbolTable::new();
+        assert_eq!(
+            parse("(\"a\" |b c|)", &mut table),
+            Ok(Sexp::List(vec![
+                Sexp::Constant(Atom::String("a".to_owned())),
+                Sexp::Constant(Atom::Symbol(table.get("b c").unwrap())),
+            ]))
+        );
+        assert_eq!(table.get("a"), None);
+    }
+
+    #[test]
+    fn resolve_gives_back_the_plain_tree() {
+        let src = "(a `(b ,c ,@d) #(1 \"s\") (e . f))";
+        let mut table = SymbolTable::new();
+        let sexp = parse(src, &mut table).unwrap();
+        assert_eq!(Ok(sexp.resolve(&table)), crate::parse::parse(src));
+    }
+}
@@ -1,6 +1,35 @@
+// The parsers spell out their input lifetimes to match nom's own signatures.
+#![allow(clippy::needless_lifetimes)]
+
 /// Provide our Symbolic Expression types and a parser, `sexp`.
 pub mod expr;
 //pub use expr::*;
 
+pub mod span;
+
+pub mod borrowed;
+
+pub mod intern;
+
+pub mod cst;
+
+pub mod error;
+pub use error::Error;
+
 pub mod parse;
+
+pub mod reader;
+pub use reader::Reader;
+
+pub mod pretty;
+
+#[cfg(feature = "serde")]
+pub mod ser;
+#[cfg(feature = "serde")]
+pub use ser::{to_sexp, to_string, to_string_pretty};
+
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "serde")]
+pub use de::{from_sexp, from_str};
 //pub use parse::sexp;
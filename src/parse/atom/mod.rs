@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 use nom::{
     branch,
     bytes,
@@ -5,70 +7,181 @@ use nom::{
     combinator,
     sequence,
     error::{
+        self,
         VerboseError,
         VerboseErrorKind,
     },
     IResult,
 };
 
-use crate::expr::{
-    Atom,
-    Num
+use crate::{
+    error::{
+        BAD_CHAR_NAME,
+        BAD_ESCAPE,
+        CLOSING_BAR,
+        CLOSING_QUOTE,
+        INTEGER_OVERFLOW,
+        INVALID_SYMBOL,
+        MALFORMED_NUMBER,
+        ZERO_DENOMINATOR,
+    },
+    borrowed,
+    expr::{
+        Atom,
+        Num,
+        Ratio,
+    },
 };
 
 // Public
 
 pub fn atom<'a>(i: &'a str) -> IResult<&'a str, Atom, VerboseError<&'a str>> {
+    combinator::map(borrowed_atom, borrowed::Atom::into_owned)(i)
+}
+
+/// `atom`, but strings and symbols borrow from `i` unless they have escapes.
+pub fn borrowed_atom<'a>(i: &'a str) -> IResult<&'a str, borrowed::Atom<'a>, VerboseError<&'a str>> {
     use combinator::map;
     branch::alt((
-        map(num, Atom::Num),
-        map(lit_char, Atom::Char),
-        map(string, Atom::String),
-        map(symbol, Atom::Symbol),
+        map(num, borrowed::Atom::Num),
+        map(boolean, borrowed::Atom::Bool),
+        map(lit_char, borrowed::Atom::Char),
+        map(string, borrowed::Atom::String),
+        map(symbol, borrowed::Atom::Symbol),
     ))(i)
 }
 
 // String parsing
 
-fn string<'a>(i: &'a str) -> IResult<&'a str, String, VerboseError<&'a str>> {
+fn string<'a>(i: &'a str) -> IResult<&'a str, Cow<'a, str>, VerboseError<&'a str>> {
     use bytes::complete::tag;
-    use sequence::{terminated, preceded};
-    terminated(preceded(tag("\""), string_inner), tag("\""))(i)
+    use sequence::terminated;
+    let (s, _) = tag("\"")(i)?;
+    // Nothing else starts with `"`, so an unclosed string is a hard failure,
+    // reported at the opening quote.
+    terminated(string_inner, tag("\""))(s).map_err(|e| match e {
+        nom::Err::Error(_) => nom::Err::Failure(VerboseError {
+            errors: vec![(i, VerboseErrorKind::Context(CLOSING_QUOTE))],
+        }),
+        e => e,
+    })
 }
 
-fn string_inner<'a>(s: &'a str) -> IResult<&'a str, String, VerboseError<&'a str>> {
-    let mut result = String::new();
-    let mut skip = false;
+fn string_inner<'a>(s: &'a str) -> IResult<&'a str, Cow<'a, str>, VerboseError<&'a str>> {
+    escaped_until('"', s)
+}
 
-    for (i, ch) in s.char_indices() {
-        if ch == '\\' && !skip {
-            skip = true;
-        } else if ch == '"' && !skip {
-            return Ok((&s[i..], result));
-        } else {
-            result.push(ch);
-            skip = false;
+// Read up to the first unescaped `end`, leaving it on the input.
+// `|symbols|` share the string escapes. Only text with escapes in it is
+// copied, the rest is borrowed.
+fn escaped_until<'a>(end: char, s: &'a str) -> IResult<&'a str, Cow<'a, str>, VerboseError<&'a str>> {
+    let mut result: Option<String> = None;
+    let mut pos = 0;
+
+    while let Some(ch) = s[pos..].chars().next() {
+        match ch {
+            c if c == end => {
+                let text = result.map_or(Cow::Borrowed(&s[..pos]), Cow::Owned);
+                return Ok((&s[pos..], text));
+            }
+            // A trailing `\` is just an unclosed string
+            '\\' if pos + 1 < s.len() => match escape(&s[pos + 1..]) {
+                Some((escaped, len)) => {
+                    result.get_or_insert_with(|| s[..pos].to_owned()).extend(escaped);
+                    pos += 1 + len;
+                }
+                None => return Err(nom::Err::Failure(VerboseError {
+                    errors: vec![(&s[pos..], VerboseErrorKind::Context(BAD_ESCAPE))],
+                })),
+            },
+            _ => {
+                if let Some(result) = &mut result {
+                    result.push(ch);
+                }
+                pos += ch.len_utf8();
+            }
         }
     }
     Err(nom::Err::Error(VerboseError {
-        errors: vec![(s, VerboseErrorKind::Context("string missing closing \""))],
+        errors: vec![(s, VerboseErrorKind::Nom(error::ErrorKind::Eof))],
     }))
 }
 
-
+// What the escape following a `\` stands for, and how many bytes it spans.
+// Line continuations stand for nothing at all.
+fn escape(i: &str) -> Option<(Option<char>, usize)> {
+    let simple = match i.chars().next()? {
+        'a'  => Some('\x07'),
+        'b'  => Some('\x08'),
+        't'  => Some('\t'),
+        'n'  => Some('\n'),
+        'r'  => Some('\r'),
+        '"'  => Some('"'),
+        '\\' => Some('\\'),
+        '|'  => Some('|'),
+        _    => None,
+    };
+    if simple.is_some() {
+        return Some((simple, 1));
+    }
+
+    // `\xHH;` with any number of hex digits
+    if let Some(hex) = i.strip_prefix('x') {
+        let end = hex.find(';')?;
+        let digits = &hex[..end];
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let c = u32::from_str_radix(digits, 16).ok().and_then(std::char::from_u32)?;
+        return Some((Some(c), 1 + end + 1));
+    }
+
+    // `\` <intraline whitespace>* <line ending> <intraline whitespace>*
+    let indent = |s: &str| s.len() - s.trim_start_matches([' ', '\t']).len();
+    let before = indent(i);
+    let rest = &i[before..];
+    let newline = if rest.starts_with("\r\n") {
+        2
+    } else if rest.starts_with('\n') {
+        1
+    } else {
+        return None;
+    };
+    Some((None, before + newline + indent(&rest[newline..])))
+}
+
+
 
 // Symbol parsing
 
-fn symbol<'a>(i: &'a str) -> IResult<&'a str, String, VerboseError<&'a str>> {
-    use combinator::map;
-    use sequence::delimited;
-    use bytes::complete::{tag, is_not};
-    branch::alt((
-        map(delimited(tag("|"), is_not("|"), tag("|")), |s: &str| {
-            s.to_owned()
+fn symbol<'a>(i: &'a str) -> IResult<&'a str, Cow<'a, str>, VerboseError<&'a str>> {
+    branch::alt((bar_symbol, plain_symbol))(i)
+}
+
+// Like a string, a `|` opens a symbol that only another `|` ends, so an
+// unclosed one fails at the first `|` instead of becoming a plain symbol.
+fn bar_symbol<'a>(i: &'a str) -> IResult<&'a str, Cow<'a, str>, VerboseError<&'a str>> {
+    use bytes::complete::tag;
+    use sequence::terminated;
+    let (s, _) = tag("|")(i)?;
+    terminated(|s| escaped_until('|', s), tag("|"))(s).map_err(|e| match e {
+        nom::Err::Error(_) => nom::Err::Failure(VerboseError {
+            errors: vec![(i, VerboseErrorKind::Context(CLOSING_BAR))],
         }),
-        map(is_not(" \t\r\n()"), |s: &str| s.to_owned()),
-    ))(i)
+        e => e,
+    })
+}
+
+// Everything else has had its chance at the token by now, so a `#` in it
+// can't be part of anything valid.
+fn plain_symbol<'a>(i: &'a str) -> IResult<&'a str, Cow<'a, str>, VerboseError<&'a str>> {
+    let (rest, name) = combinator::verify(token, |t: &str| !t.is_empty())(i)?;
+    if name.contains('#') {
+        return Err(nom::Err::Failure(VerboseError {
+            errors: vec![(i, VerboseErrorKind::Context(INVALID_SYMBOL))],
+        }));
+    }
+    Ok((rest, Cow::Borrowed(name)))
 }
 
 
@@ -76,91 +189,355 @@ fn symbol<'a>(i: &'a str) -> IResult<&'a str, String, VerboseError<&'a str>> {
 // Number parsing
 
 fn num<'a>(i: &'a str) -> IResult<&'a str, Num, VerboseError<&'a str>> {
-    use combinator::map_res;
+    let (rest, token) = combinator::verify(token, |t: &str| !t.is_empty())(i)?;
+    let result = match read_number(token) {
+        Some(result) => result,
+        // `123abc` and `1.2.3` aren't symbols either
+        None if looks_numeric(token) => Err(MALFORMED_NUMBER),
+        // Let the symbol parser have it
+        None => return Err(nom::Err::Error(VerboseError {
+            errors: vec![(i, VerboseErrorKind::Nom(error::ErrorKind::Digit))],
+        })),
+    };
+    result.map(|n| (rest, n)).map_err(|label| nom::Err::Failure(VerboseError {
+        errors: vec![(i, VerboseErrorKind::Context(label))],
+    }))
+}
+
+// Whether a token that isn't a number was probably meant to be one: it has a
+// radix or exactness prefix, or a digit right after the sign or leading `.`.
+fn looks_numeric(token: &str) -> bool {
+    let mut rest = token;
+    let mut prefixed = false;
+    while let Some(prefix) = rest.strip_prefix('#') {
+        match prefix.chars().next() {
+            Some(c) if "xdobeiXDOBEI".contains(c) => rest = &prefix[1..],
+            _ => break,
+        }
+        prefixed = true;
+    }
+    let unsigned = rest.strip_prefix(['+', '-']).unwrap_or(rest);
+    let starts = |word: &str| unsigned.get(..word.len()).is_some_and(|s| s.eq_ignore_ascii_case(word));
+    let first = unsigned.strip_prefix('.').unwrap_or(unsigned).chars().next();
+    first.is_some_and(|c| c.is_ascii_digit())
+        || (prefixed && (first.is_some_and(|c| c.is_ascii_hexdigit())
+            || starts("inf.")
+            || starts("nan.")))
+}
+
+// R7RS numeric syntax for a whole token:
+//
+//   number  = prefix [+-] (uinteger | uinteger/uinteger | decimal)
+//           | prefix [+-] (inf.0 | nan.0)
+//   prefix  = radix and exactness in either order, `#x #o #b #d` and `#e #i`
+//   decimal = `1.5`, `.5`, `5.` and `1e10`, all base 10 only
+//
+// Digits may also be split up with `_`, as in `1_000`. `None` means the token
+// isn't a number, and `Some(Err(_))` names the context for one we can't hold.
+fn read_number(token: &str) -> Option<Result<Num, &'static str>> {
+    let mut radix = None;
+    let mut exact = None;
+    let mut rest = token;
+    while let Some(prefix) = rest.strip_prefix('#') {
+        let mut chars = prefix.chars();
+        match chars.next()?.to_ascii_lowercase() {
+            'x' if radix.is_none() => radix = Some(16),
+            'd' if radix.is_none() => radix = Some(10),
+            'o' if radix.is_none() => radix = Some(8),
+            'b' if radix.is_none() => radix = Some(2),
+            'e' if exact.is_none() => exact = Some(true),
+            'i' if exact.is_none() => exact = Some(false),
+            _ => return None,
+        }
+        rest = chars.as_str();
+    }
+    let radix = radix.unwrap_or(10);
+
+    let (negative, unsigned) = match rest.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, rest.strip_prefix('+').unwrap_or(rest)),
+    };
+    // The infinities and NaN need their sign, so `inf.0` stays a symbol
+    if unsigned.len() < rest.len() && exact != Some(true) {
+        if unsigned.eq_ignore_ascii_case("inf.0") {
+            let inf = if negative { f64::NEG_INFINITY } else { f64::INFINITY };
+            return Some(Ok(Num::Float(inf)));
+        }
+        if unsigned.eq_ignore_ascii_case("nan.0") {
+            return Some(Ok(Num::Float(f64::NAN)));
+        }
+    }
+
+    if let Some((numer, denom)) = unsigned.split_once('/') {
+        return Some(ratio(negative, &digits(numer, radix)?, &digits(denom, radix)?, radix, exact));
+    }
+    if radix != 10 {
+        return Some(integer(negative, &digits(unsigned, radix)?, radix, exact));
+    }
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(e) => (&unsigned[..e], Some(&unsigned[e + 1..])),
+        None => (unsigned, None),
+    };
+    let (whole, fraction) = match mantissa.find('.') {
+        Some(dot) => (&mantissa[..dot], Some(&mantissa[dot + 1..])),
+        None => (mantissa, None),
+    };
+    // One side of the `.` may be empty, but not both
+    let (whole, fraction) = match fraction {
+        None => (digits(whole, 10)?, None),
+        Some(f) if whole.is_empty() => (Cow::Borrowed(""), Some(digits(f, 10)?)),
+        Some("") => (digits(whole, 10)?, Some(Cow::Borrowed(""))),
+        Some(f) => (digits(whole, 10)?, Some(digits(f, 10)?)),
+    };
+    let exponent = match exponent {
+        Some(e) => {
+            let (sign, e) = match e.strip_prefix('-') {
+                Some(e) => ("-", e),
+                None => ("", e.strip_prefix('+').unwrap_or(e)),
+            };
+            Some(format!("{}{}", sign, digits(e, 10)?))
+        }
+        None => None,
+    };
+
+    if fraction.is_none() && exponent.is_none() {
+        return Some(integer(negative, &whole, 10, exact));
+    }
+    let fraction = fraction.unwrap_or_default();
+    let exponent = exponent.unwrap_or_else(|| "0".to_owned());
+    if exact == Some(true) {
+        return Some(exact_decimal(negative, &whole, &fraction, &exponent));
+    }
+    let float = format!("{}0{}.{}0e{}", if negative { "-" } else { "" }, whole, fraction, exponent);
+    Some(Ok(Num::Float(float.parse().expect("validated float syntax"))))
+}
+
+// Digits in `radix` with single `_`s allowed between them, which get dropped.
+// Only a number with `_`s in it needs a copy.
+fn digits(s: &str, radix: u32) -> Option<Cow<'_, str>> {
+    let mut after_digit = false;
+    for c in s.bytes() {
+        if (c as char).is_digit(radix) {
+            after_digit = true;
+        } else if c == b'_' && after_digit {
+            after_digit = false;
+        } else {
+            return None;
+        }
+    }
+    match after_digit {
+        false => None,
+        true if s.contains('_') => Some(Cow::Owned(s.replace('_', ""))),
+        true => Some(Cow::Borrowed(s)),
+    }
+}
+
+fn integer(negative: bool, digits: &str, radix: u32, exact: Option<bool>) -> Result<Num, &'static str> {
+    let signed = if negative {
+        format!("-{}", digits)
+    } else {
+        digits.to_owned()
+    };
+    if exact == Some(false) {
+        let magnitude = inexact(digits, radix);
+        return Ok(Num::Float(if negative { -magnitude } else { magnitude }));
+    }
+    match i64::from_str_radix(&signed, radix) {
+        Ok(n) => Ok(Num::Int(n)),
+        // The digits are all valid, so it can only have overflowed
+        Err(_) => big_integer(&signed, radix).ok_or(INTEGER_OVERFLOW),
+    }
+}
+
+fn inexact(digits: &str, radix: u32) -> f64 {
+    digits
+        .chars()
+        .fold(0.0, |n, d| n * radix as f64 + d.to_digit(radix).unwrap_or(0) as f64)
+}
+
+// `n/d`, which is only ever exact, unless it's `#i`. Both halves have to fit
+// in an `i64`, with or without the `bigint` feature.
+fn ratio(negative: bool, numer: &str, denom: &str, radix: u32, exact: Option<bool>) -> Result<Num, &'static str> {
+    if denom.chars().all(|d| d == '0') {
+        return Err(ZERO_DENOMINATOR);
+    }
+    if exact == Some(false) {
+        let magnitude = inexact(numer, radix) / inexact(denom, radix);
+        return Ok(Num::Float(if negative { -magnitude } else { magnitude }));
+    }
+    let sign = if negative { "-" } else { "" };
+    let numer = i64::from_str_radix(&format!("{}{}", sign, numer), radix)
+        .map_err(|_| INTEGER_OVERFLOW)?;
+    let denom = i64::from_str_radix(denom, radix).map_err(|_| INTEGER_OVERFLOW)?;
+    exact_ratio(numer, denom)
+}
+
+// Whole ratios like `4/2` read as plain integers.
+fn exact_ratio(numer: i64, denom: i64) -> Result<Num, &'static str> {
+    match Ratio::new(numer, denom) {
+        Some(r) if r.denom() == 1 => Ok(Num::Int(r.numer())),
+        Some(r) => Ok(Num::Ratio(r)),
+        None => Err(INTEGER_OVERFLOW),
+    }
+}
+
+// `#e` on a decimal: the digits of both halves as one integer, scaled by the
+// exponent.
+fn exact_decimal(negative: bool, whole: &str, fraction: &str, exponent: &str) -> Result<Num, &'static str> {
+    use std::convert::TryFrom;
+    let mut mantissa = format!("{}{}", whole, fraction);
+    let mut scale = exponent
+        .parse::<i64>()
+        .ok()
+        .and_then(|e| e.checked_sub(fraction.len() as i64))
+        .ok_or(INTEGER_OVERFLOW)?;
+    while scale < 0 && mantissa.ends_with('0') {
+        mantissa.pop();
+        scale += 1;
+    }
+    if mantissa.trim_start_matches('0').is_empty() {
+        return Ok(Num::Int(0));
+    }
+    if scale < 0 {
+        // `1.25` is `125/100`, and `10^19` is already too big
+        let denom = scale
+            .checked_neg()
+            .and_then(|s| u32::try_from(s).ok())
+            .and_then(|s| 10i64.checked_pow(s))
+            .ok_or(INTEGER_OVERFLOW)?;
+        let sign = if negative { "-" } else { "" };
+        let numer = format!("{}{}", sign, mantissa).parse().map_err(|_| INTEGER_OVERFLOW)?;
+        return exact_ratio(numer, denom);
+    }
+    // Far past anything an `i64` or a sane bigint holds
+    if scale > 4096 {
+        return Err(INTEGER_OVERFLOW);
+    }
+    mantissa.extend(std::iter::repeat_n('0', scale as usize));
+    integer(negative, &mantissa, 10, Some(true))
+}
+
+#[cfg(feature = "bigint")]
+fn big_integer(digits: &str, radix: u32) -> Option<Num> {
+    num_bigint::BigInt::parse_bytes(digits.as_bytes(), radix).map(Num::BigInt)
+}
+
+#[cfg(not(feature = "bigint"))]
+fn big_integer(_: &str, _: u32) -> Option<Num> {
+    None
+}
+
+
+
+// Boolean parsing
+
+fn boolean<'a>(i: &'a str) -> IResult<&'a str, bool, VerboseError<&'a str>> {
     use bytes::complete::tag;
-    use character::complete::digit1;
-    use sequence::{preceded, separated_pair};
-    branch::alt((
-        // Floats
-        map_res(
-            separated_pair(digit1, tag("."), digit1),
-            |(whole, part): (&str, &str)| {
-                (whole.to_owned() + "." + part)
-                    .parse::<f64>()
-                    .map(Num::Float)
-            },
-        ),
-        map_res(
-            preceded(tag("-"), separated_pair(digit1, tag("."), digit1)),
-            |(whole, part): (&str, &str)| {
-                (whole.to_owned() + "." + part)
-                    .parse::<f64>()
-                    .map(|f| Num::Float(-f))
-            },
-        ),
-        // Ints
-        map_res(digit1, |d: &str| d.parse::<i64>().map(Num::Int)),
-        map_res(preceded(tag("-"), digit1), |d: &str| {
-            d.parse::<i64>().map(|i| Num::Int(-i))
-        }),
-    ))(i)
+    use combinator::{map_opt, recognize};
+    use sequence::pair;
+    // Read the whole token, so `#tab` isn't `#t` followed by `ab`
+    map_opt(recognize(pair(tag("#"), token)), |b: &str| match b {
+        "#t" | "#true" => Some(true),
+        "#f" | "#false" => Some(false),
+        _ => None,
+    })(i)
 }
 
-
+
+
+// Tokens
+
+/// Whitespace, parens, brackets, quotes and `;` end a token.
+pub(crate) fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || "()[]\"'`,;".contains(c)
+}
+
+/// The run of chars up to the next delimiter or `#|` and `#;` comment, which
+/// may be empty. Numbers, booleans, chars and symbols all read a whole token,
+/// so one atom can't run into the next.
+pub(crate) fn token<'a>(i: &'a str) -> IResult<&'a str, &'a str, VerboseError<&'a str>> {
+    let end = i
+        .char_indices()
+        .find(|&(n, c)| is_delimiter(c) || i[n..].starts_with("#|") || i[n..].starts_with("#;"))
+        .map_or(i.len(), |(n, _)| n);
+    Ok((&i[end..], &i[..end]))
+}
+
+
 
 // Character literal parsing
 
 fn lit_char<'a>(i: &'a str) -> IResult<&'a str, char, VerboseError<&'a str>> {
-    use combinator::map;
-    use sequence::preceded;
-    use bytes::complete::{tag, is_not};
-    map(preceded(tag("#\\"), is_not(" \t\r\n")), process_char)(i)
+    use bytes::complete::tag;
+    use character::complete::anychar;
+    use combinator::{cut, recognize};
+    use sequence::{pair, preceded};
+    // The first char is taken as is, so `#\(` and `#\ ` work, then a name
+    // runs up to the next delimiter.
+    let (rest, name) = preceded(
+        tag("#\\"),
+        error::context(
+            BAD_CHAR_NAME,
+            cut(recognize(pair(anychar, token)))),
+    )(i)?;
+    match process_char(name) {
+        Some(c) => Ok((rest, c)),
+        None => Err(nom::Err::Failure(VerboseError {
+            errors: vec![(i, VerboseErrorKind::Context(BAD_CHAR_NAME))],
+        })),
+    }
 }
 
-// Currently this panics if the char is invalid
-fn process_char(i: &str) -> char {
-    if i.len() == 1 {
-        i.chars().next().unwrap()
+// `None` if `i` is neither a single char, a hex scalar value nor a known name
+fn process_char(i: &str) -> Option<char> {
+    let mut chars = i.chars();
+    let first = chars.next()?;
+    let rest = chars.as_str();
+    if rest.is_empty() {
+        Some(first)
+    } else if first == 'x' && rest.chars().all(|c| c.is_ascii_hexdigit()) {
+        u32::from_str_radix(rest, 16).ok().and_then(std::char::from_u32)
     } else {
-        match i {
-            "nul"   => 0x00 as char,
+        let code = match i {
+            "nul"   => 0x00u8,
             // Short names for the `C0` characters
-            "soh"                          => 0x01 as char,
-            "stx"                          => 0x02 as char,
-            "etx"                          => 0x03 as char,
-            "eot"                          => 0x04 as char,
-            "enq"                          => 0x05 as char,
-            "ack"                          => 0x06 as char,
-            "bel" | "alarm"                => 0x07 as char,
-            "bs"  | "backspace"            => 0x08 as char,
-            "ht"  | "tab"                  => 0x09 as char,
-            "lf"  | "linefeed" | "newline" => 0x0A as char,
-            "vt"  | "vtab"                 => 0x0B as char,
-            "ff"  | "page"                 => 0x0C as char,
-            "cr"  | "return"               => 0x0D as char,
-            "so"                           => 0x0E as char,
-            "si"                           => 0x0F as char,
-            "dle"                          => 0x10 as char,
-            "dc1"                          => 0x11 as char,
-            "dc2"                          => 0x12 as char,
-            "dc3"                          => 0x13 as char,
-            "dc4"                          => 0x14 as char,
-            "nak"                          => 0x15 as char,
-            "syn"                          => 0x16 as char,
-            "etb"                          => 0x17 as char,
-            "can"                          => 0x18 as char,
-            "em"                           => 0x19 as char,
-            "sub"                          => 0x1A as char,
-            "esc" | "escape" /* R7RS */    => 0x1B as char,
-            "fs"                           => 0x1C as char,
-            "gs"                           => 0x1D as char,
-            "rs"                           => 0x1E as char,
-            "us"                           => 0x1F as char,
-            "sp"  | "space"                => 0x20 as char,
+            "soh"                          => 0x01,
+            "stx"                          => 0x02,
+            "etx"                          => 0x03,
+            "eot"                          => 0x04,
+            "enq"                          => 0x05,
+            "ack"                          => 0x06,
+            "bel" | "alarm"                => 0x07,
+            "bs"  | "backspace"            => 0x08,
+            "ht"  | "tab"                  => 0x09,
+            "lf"  | "linefeed" | "newline" => 0x0A,
+            "vt"  | "vtab"                 => 0x0B,
+            "ff"  | "page"                 => 0x0C,
+            "cr"  | "return"               => 0x0D,
+            "so"                           => 0x0E,
+            "si"                           => 0x0F,
+            "dle"                          => 0x10,
+            "dc1"                          => 0x11,
+            "dc2"                          => 0x12,
+            "dc3"                          => 0x13,
+            "dc4"                          => 0x14,
+            "nak"                          => 0x15,
+            "syn"                          => 0x16,
+            "etb"                          => 0x17,
+            "can"                          => 0x18,
+            "em"                           => 0x19,
+            "sub"                          => 0x1A,
+            "esc" | "escape" /* R7RS */    => 0x1B,
+            "fs"                           => 0x1C,
+            "gs"                           => 0x1D,
+            "rs"                           => 0x1E,
+            "us"                           => 0x1F,
+            "sp"  | "space"                => 0x20,
             // End of `C0` characters
-            "del" | "delete"               => 0x7F as char,
-            _ => panic!("Should find a way to handle this more gracefully"),
-        }
+            "del" | "delete"               => 0x7F,
+            _ => return None,
+        };
+        Some(code as char)
     }
 }
 
@@ -176,7 +553,7 @@ mod tests {
     fn parse_whole_scm_string() {
         assert_eq!(
             string("\"This is a test\""),
-            Ok(("", "This is a test".to_owned()))
+            Ok(("", "This is a test".into()))
         );
     }
 
@@ -184,15 +561,61 @@ mod tests {
     fn parse_scm_string_with_escaped_quotes() {
         assert_eq!(
             string("\"This is a \\\"test\\\"\""),
-            Ok(("", "This is a \"test\"".to_owned()))
+            Ok(("", "This is a \"test\"".into()))
         );
         // With unclosed escaped string too
         assert_eq!(
             string("\"This is a \\\"test\" and some more stuff"),
-            Ok((" and some more stuff", "This is a \"test".to_owned()))
+            Ok((" and some more stuff", "This is a \"test".into()))
         );
     }
 
+    #[test]
+    fn parse_scm_string_escapes() {
+        assert_eq!(
+            string(r#""\a\b\t\n\r\"\\\|""#),
+            Ok(("", "\x07\x08\t\n\r\"\\|".into()))
+        );
+    }
+
+    #[test]
+    fn parse_scm_string_hex_escapes() {
+        assert_eq!(
+            string(r#""\x41;\x3bb;\x0;\x1F600;""#),
+            Ok(("", "Aλ\0😀".into()))
+        );
+        // The `;` is required, and the value must be a scalar value
+        assert!(string(r#""\x41""#).is_err());
+        assert!(string(r#""\x;""#).is_err());
+        assert!(string(r#""\xD800;""#).is_err());
+        assert!(string(r#""\x110000;""#).is_err());
+    }
+
+    #[test]
+    fn parse_scm_string_line_continuations() {
+        assert_eq!(
+            string("\"one \\\n    two\""),
+            Ok(("", "one two".into()))
+        );
+        assert_eq!(
+            string("\"one \\  \t\r\n\ttwo\""),
+            Ok(("", "one two".into()))
+        );
+        // Only one line ending is swallowed
+        assert_eq!(
+            string("\"one\\\n\n two\""),
+            Ok(("", "one\n two".into()))
+        );
+    }
+
+    #[test]
+    fn unknown_string_escapes_fail() {
+        assert!(matches!(string(r#""\q""#), Err(nom::Err::Failure(_))));
+        assert!(matches!(string(r#""\ x""#), Err(nom::Err::Failure(_))));
+        // A backslash at the very end is an unclosed string
+        assert!(matches!(string(r#""abc\"#), Err(nom::Err::Failure(_))));
+    }
+
     #[test]
     fn fail_to_parse_scm_string_without_active_quotes() {
         assert!(string("this is a test").is_err());
@@ -212,6 +635,33 @@ mod tests {
         assert_eq!(num("-562"), Ok(("", Num::Int(-562))));
     }
 
+    #[test]
+    fn parse_extreme_integers() {
+        assert_eq!(num("9223372036854775807"), Ok(("", Num::Int(i64::MAX))));
+        assert_eq!(num("-9223372036854775808"), Ok(("", Num::Int(i64::MIN))));
+    }
+
+    #[cfg(not(feature = "bigint"))]
+    #[test]
+    fn integer_overflow_fails() {
+        assert!(matches!(num("9223372036854775808"), Err(nom::Err::Failure(_))));
+        assert!(matches!(atom("-99999999999999999999"), Err(nom::Err::Failure(_))));
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn integer_overflow_is_a_bigint() {
+        use num_bigint::BigInt;
+        assert_eq!(
+            num("99999999999999999999 rest"),
+            Ok((" rest", Num::BigInt("99999999999999999999".parse::<BigInt>().unwrap())))
+        );
+        assert_eq!(
+            num("-9223372036854775809"),
+            Ok(("", Num::BigInt(BigInt::from(i64::MIN) - 1)))
+        );
+    }
+
     #[test]
     fn parse_positive_float() {
         assert_eq!(num("67.432"), Ok(("", Num::Float(67.432))));
@@ -226,19 +676,19 @@ mod tests {
 
     #[test]
     fn parse_simple_symbols() {
-        assert_eq!(symbol("map"), Ok(("", "map".to_owned())));
+        assert_eq!(symbol("map"), Ok(("", "map".into())));
         assert_eq!(
-            symbol("^!symbols#$%legal"),
-            Ok(("", "^!symbols#$%legal".to_owned()))
+            symbol("^!symbols$%legal"),
+            Ok(("", "^!symbols$%legal".into()))
         );
-        assert_eq!(symbol("regular-name"), Ok(("", "regular-name".to_owned())));
+        assert_eq!(symbol("regular-name"), Ok(("", "regular-name".into())));
     }
 
     #[test]
     fn only_get_first_symbol() {
         assert_eq!(
             symbol("this is a test"),
-            Ok((" is a test", "this".to_owned()))
+            Ok((" is a test", "this".into()))
         );
     }
 
@@ -246,34 +696,66 @@ mod tests {
     fn symbol_cannot_contain_parens() {
         assert_eq!(
             symbol("symbol("),
-            Ok(("(", "symbol".to_owned()))
+            Ok(("(", "symbol".into()))
         );
 
         assert_eq!(
             symbol("fun-an)d-games"),
-            Ok((")d-games", "fun-an".to_owned()))
+            Ok((")d-games", "fun-an".into()))
         );
     }
 
+    #[test]
+    fn symbol_stops_at_comment() {
+        assert_eq!(
+            symbol("symbol; comment"),
+            Ok(("; comment", "symbol".into()))
+        );
+        assert_eq!(symbol("symbol#| comment |#"), Ok(("#| comment |#", "symbol".into())));
+        assert_eq!(symbol("symbol#;datum"), Ok(("#;datum", "symbol".into())));
+    }
+
+    #[test]
+    fn symbol_stops_at_quotes_and_brackets() {
+        assert_eq!(symbol("a\"b\""), Ok(("\"b\"", "a".into())));
+        assert_eq!(symbol("a'b"), Ok(("'b", "a".into())));
+        assert_eq!(symbol("a`b"), Ok(("`b", "a".into())));
+        assert_eq!(symbol("a,b"), Ok((",b", "a".into())));
+        assert_eq!(symbol("a[b]"), Ok(("[b]", "a".into())));
+    }
+
+    #[test]
+    fn symbol_cannot_contain_hash() {
+        assert!(matches!(symbol("a#b"), Err(nom::Err::Failure(_))));
+        assert!(matches!(symbol("#foo"), Err(nom::Err::Failure(_))));
+    }
+
     #[test]
     fn parse_delimited_symbol() {
         assert_eq!(
             symbol("|this is a symbol|"),
-            Ok(("", "this is a symbol".to_owned()))
+            Ok(("", "this is a symbol".into()))
         );
     }
 
     #[test]
-    fn delimited_symbol_with_unmatched_delimiters() {
-        assert_eq!(symbol("|this"), Ok(("", "|this".to_owned())));
+    fn delimited_symbol_with_escapes() {
         assert_eq!(
-            symbol("|these are many symbols"),
-            Ok((" are many symbols", "|these".to_owned()))
+            symbol(r"|a \| b\x41;\\|"),
+            Ok(("", "a | bA\\".into()))
         );
-        assert_eq!(symbol("this|"), Ok(("", "this|".to_owned())));
+        assert_eq!(symbol("||"), Ok(("", "".into())));
+    }
+
+    #[test]
+    fn delimited_symbol_with_unmatched_delimiters() {
+        // A leading `|` needs its closing one, like a string's `"`
+        assert!(matches!(symbol("|this"), Err(nom::Err::Failure(_))));
+        assert!(matches!(symbol("|these are many symbols"), Err(nom::Err::Failure(_))));
+        assert_eq!(symbol("this|"), Ok(("", "this|".into())));
         assert_eq!(
             symbol("this|is many symbols"),
-            Ok((" many symbols", "this|is".to_owned()))
+            Ok((" many symbols", "this|is".into()))
         );
     }
 
@@ -286,6 +768,20 @@ mod tests {
         assert_eq!(num("-562"), Ok(("", Num::Int(-562))));
     }
 
+    #[test]
+    fn num_reads_the_whole_token() {
+        assert_eq!(num("12)"), Ok((")", Num::Int(12))));
+        assert_eq!(num("12;x"), Ok((";x", Num::Int(12))));
+        assert_eq!(num("12\"x\""), Ok(("\"x\"", Num::Int(12))));
+        for src in &["123abc", "12.5.3", "1+", "-1x", ".5.", "#x1G", "#e1.2.3"] {
+            assert!(matches!(num(src), Err(nom::Err::Failure(_))), "{}", src);
+        }
+        // These are left for the symbol parser
+        for src in &["abc", "+", "-x", "...", "_1"] {
+            assert!(matches!(num(src), Err(nom::Err::Error(_))), "{}", src);
+        }
+    }
+
     #[test]
     fn num_float() {
         assert_eq!(num("67.432"), Ok(("", Num::Float(67.432))));
@@ -365,14 +861,63 @@ mod tests {
     }
 
 
+    #[test]
+    fn unicode_char_literals() {
+        assert_eq!(atom("#\\λ"), Ok(("", Atom::Char('λ'))));
+        assert_eq!(atom("#\\😀 x"), Ok((" x", Atom::Char('😀'))));
+    }
+
+    #[test]
+    fn hex_char_literals() {
+        assert_eq!(atom("#\\x41"), Ok(("", Atom::Char('A'))));
+        assert_eq!(atom("#\\x3bb"), Ok(("", Atom::Char('λ'))));
+        assert_eq!(atom("#\\x"), Ok(("", Atom::Char('x'))));
+        // Surrogates aren't scalar values
+        assert!(atom("#\\xD800").is_err());
+    }
+
+    #[test]
+    fn char_literals_stop_at_delimiters() {
+        assert_eq!(atom("#\\a)"), Ok((")", Atom::Char('a'))));
+        assert_eq!(atom("#\\space(b)"), Ok(("(b)", Atom::Char(' '))));
+        assert_eq!(atom("#\\x41;hex"), Ok((";hex", Atom::Char('A'))));
+        // Unless the delimiter is the char itself
+        assert_eq!(atom("#\\()"), Ok((")", Atom::Char('('))));
+        assert_eq!(atom("#\\)"), Ok(("", Atom::Char(')'))));
+        assert_eq!(atom("#\\ "), Ok(("", Atom::Char(' '))));
+    }
+
+    #[test]
+    fn unknown_char_names_fail() {
+        assert!(matches!(atom("#\\foo"), Err(nom::Err::Failure(_))));
+        assert!(matches!(atom("#\\xyz"), Err(nom::Err::Failure(_))));
+        assert!(matches!(atom("#\\"), Err(nom::Err::Failure(_))));
+    }
+
+    // Booleans
+
+    #[test]
+    fn boolean_literals() {
+        assert_eq!(atom("#t"), Ok(("", Atom::Bool(true))));
+        assert_eq!(atom("#true"), Ok(("", Atom::Bool(true))));
+        assert_eq!(atom("#f"), Ok(("", Atom::Bool(false))));
+        assert_eq!(atom("#false)"), Ok((")", Atom::Bool(false))));
+    }
+
+    #[test]
+    fn boolean_prefixed_tokens_fail() {
+        assert!(matches!(atom("#tab"), Err(nom::Err::Failure(_))));
+        assert!(matches!(atom("#falsey"), Err(nom::Err::Failure(_))));
+    }
+
     // Symbols
 
     #[test]
     fn atom_symbols() {
         assert_eq!(atom("map"), Ok(("", Atom::Symbol("map".to_owned()))));
         assert_eq!(
-            atom("^!symbols#$%legal"),
-            Ok(("", Atom::Symbol("^!symbols#$%legal".to_owned())))
+            atom("^!symbols$%legal"),
+            Ok(("", Atom::Symbol("^!symbols$%legal".to_owned())))
         );
         assert_eq!(atom("regular-name"), Ok(("", Atom::Symbol("regular-name".to_owned()))));
         // only get first symbol
@@ -386,11 +931,8 @@ mod tests {
             Ok(("", Atom::Symbol("this is a symbol".to_owned())))
         );
         // delimited symbol with unmatched delimiters
-        assert_eq!(atom("|this"), Ok(("", Atom::Symbol("|this".to_owned()))));
-        assert_eq!(
-            atom("|these are many symbols"),
-            Ok((" are many symbols", Atom::Symbol("|these".to_owned())))
-        );
+        assert!(matches!(atom("|this"), Err(nom::Err::Failure(_))));
+        assert!(matches!(atom("|these are many symbols"), Err(nom::Err::Failure(_))));
         assert_eq!(atom("this|"), Ok(("", Atom::Symbol("this|".to_owned()))));
         assert_eq!(
             atom("this|is many symbols"),
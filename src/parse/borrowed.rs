@@ -0,0 +1,112 @@
+/// The same grammar as `parse::sexp`, but reading into `borrowed::Sexp` so
+/// that symbols and strings point back into the source.
+use nom::{
+    IResult,
+    branch,
+    multi,
+    combinator,
+    error::{ self, VerboseError },
+    sequence,
+};
+
+use crate::{
+    borrowed::{Atom, Quote, Sexp},
+    error::{ Error, END_OF_INPUT },
+};
+
+use super::{
+    atom::borrowed_atom,
+    blank,
+    compound::{ self, Parts, Tree },
+    ParseOptions,
+};
+
+pub fn sexp<'a>(i: &'a str) -> IResult<&'a str, Sexp<'a>, VerboseError<&'a str>> {
+    use combinator::map;
+    branch::alt((
+        map(quote, Sexp::Quote),
+        |j| compound::list(&Borrowed, j),
+        map(|j| compound::vector(&Borrowed, j), Sexp::Vector),
+        compound::stray_dot,
+        // `atom` is very greedy, so it needs to come last
+        map(borrowed_atom, Sexp::Constant),
+    ))(i)
+}
+
+/// Parse every top level datum in `i`, failing if anything but whitespace is left over.
+pub fn program<'a>(i: &'a str) -> IResult<&'a str, Vec<Sexp<'a>>, VerboseError<&'a str>> {
+    use sequence::{preceded, terminated};
+    terminated(
+        multi::many0(preceded(blank, sexp)),
+        preceded(
+            blank,
+            error::context(END_OF_INPUT, combinator::eof)),
+    )(i)
+}
+
+/// Parse a source text that holds exactly one datum, within the default
+/// `ParseOptions`.
+pub fn parse<'a>(i: &'a str) -> Result<Sexp<'a>, Error> {
+    ParseOptions::default().check(i)?;
+    use sequence::{delimited, terminated};
+    terminated(
+        delimited(blank, sexp, blank),
+        error::context(END_OF_INPUT, combinator::eof),
+    )(i)
+        .map(|(_, sexp)| sexp)
+        .map_err(|e| Error::from_nom(i, e))
+}
+
+/// Parse a whole source text into its top level datums, within the default
+/// `ParseOptions`.
+pub fn parse_all<'a>(i: &'a str) -> Result<Vec<Sexp<'a>>, Error> {
+    ParseOptions::default().check(i)?;
+    program(i)
+        .map(|(_, sexps)| sexps)
+        .map_err(|e| Error::from_nom(i, e))
+}
+
+pub(crate) fn quote<'a>(i: &'a str) -> IResult<&'a str, Quote<'a>, VerboseError<&'a str>> {
+    compound::quote(&Borrowed, i)
+}
+
+// Reads straight into `Sexp`, which has nothing to add around a node.
+struct Borrowed;
+
+impl<'a> Tree<'a> for Borrowed {
+    type Item = Sexp<'a>;
+    type Node = Sexp<'a>;
+    type Quote = Quote<'a>;
+
+    fn datum(&self, i: &'a str) -> IResult<&'a str, Sexp<'a>, VerboseError<&'a str>> {
+        sexp(i)
+    }
+
+    fn is_literal(item: &Sexp<'a>) -> bool {
+        matches!(item, Sexp::Constant(a) if !matches!(a, Atom::Symbol(_)))
+    }
+
+    fn quote(prefix: &str, quoted: Box<Sexp<'a>>) -> Quote<'a> {
+        match prefix {
+            "'" => Quote::Quote(quoted),
+            "`" => Quote::Quasi(quoted),
+            "," => Quote::UnQuote(quoted),
+            _ => Quote::Splice(quoted),
+        }
+    }
+
+    fn list(items: Vec<Sexp<'a>>, tail: Option<Box<Sexp<'a>>>) -> Sexp<'a> {
+        match tail {
+            Some(tail) => Sexp::DottedList(items, tail),
+            None => Sexp::List(items),
+        }
+    }
+
+    fn unpack(item: Sexp<'a>) -> Result<Parts<Sexp<'a>>, Sexp<'a>> {
+        match item {
+            Sexp::List(items) => Ok((items, None)),
+            Sexp::DottedList(items, tail) => Ok((items, Some(tail))),
+            item => Err(item),
+        }
+    }
+}
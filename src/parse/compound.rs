@@ -0,0 +1,123 @@
+/// The list, vector and quote grammar, shared by the parsers that build
+/// different trees so that they can't drift apart.
+use nom::{
+    IResult,
+    branch,
+    bytes,
+    multi,
+    combinator,
+    error::{ self, VerboseError },
+    sequence,
+    character::complete,
+};
+
+use crate::error::{
+    DOTTED_TAIL,
+    QUOTED_DATUM,
+    SPLICE_LITERAL,
+    UNQUOTE_LITERAL,
+};
+
+use super::{
+    blank,
+    bounce,
+    close,
+    dot,
+    misplaced_dot,
+};
+
+/// How a parser reads its datums and builds its nodes.
+pub(crate) trait Tree<'a> {
+    /// A datum as it's kept in a list.
+    type Item;
+    /// A list, before `datum` makes it an `Item`.
+    type Node;
+    type Quote;
+
+    /// Parse one datum.
+    fn datum(&self, i: &'a str) -> IResult<&'a str, Self::Item, VerboseError<&'a str>>;
+
+    /// Whether `item` is an atom other than a symbol.
+    fn is_literal(item: &Self::Item) -> bool;
+
+    /// `quoted` behind `prefix`, which is one of `'`, `` ` ``, `,` or `,@`.
+    fn quote(prefix: &str, quoted: Box<Self::Item>) -> Self::Quote;
+
+    /// `(items . tail)`, or a proper list without a `tail`.
+    fn list(items: Vec<Self::Item>, tail: Option<Box<Self::Item>>) -> Self::Node;
+
+    /// The items and tail of `item` if it's a list, or else `item` back.
+    fn unpack(item: Self::Item) -> Result<Parts<Self::Item>, Self::Item>;
+}
+
+/// The items of a list and its tail after the `.`, if it has one.
+pub(crate) type Parts<I> = (Vec<I>, Option<Box<I>>);
+
+pub(crate) fn quote<'a, T: Tree<'a>>(tree: &T, i: &'a str) -> IResult<&'a str, T::Quote, VerboseError<&'a str>> {
+    use bytes::complete::tag;
+    let (rest, prefix) = branch::alt((tag("'"), tag("`"), tag(",@"), tag(",")))(i)?;
+    let (rest, quoted) = error::context(
+        QUOTED_DATUM,
+        combinator::cut(|j| tree.datum(j)))(rest)?;
+    // Symbols stand for variables, so only the other atoms are literals
+    let literal = T::is_literal(&quoted);
+    match prefix {
+        "," if literal => Err(bounce(rest, UNQUOTE_LITERAL)),
+        ",@" if literal => Err(bounce(rest, SPLICE_LITERAL)),
+        _ => Ok((rest, T::quote(prefix, Box::new(quoted)))),
+    }
+}
+
+pub(crate) fn list<'a, T: Tree<'a>>(tree: &T, i: &'a str) -> IResult<&'a str, T::Node, VerboseError<&'a str>> {
+    let (rest, (items, tail)) = items(tree, true, i)?;
+    Ok((rest, match tail {
+        Some(tail) => dotted::<T>(items, tail),
+        None => T::list(items, None),
+    }))
+}
+
+pub(crate) fn vector<'a, T: Tree<'a>>(tree: &T, i: &'a str) -> IResult<&'a str, Vec<T::Item>, VerboseError<&'a str>> {
+    let (rest, (items, _)) = sequence::preceded(complete::char('#'), |j| items(tree, false, j))(i)?;
+    Ok((rest, items))
+}
+
+/// A `.` where a datum should start.
+pub(crate) fn stray_dot<'a, O>(i: &'a str) -> IResult<&'a str, O, VerboseError<&'a str>> {
+    dot(i)?;
+    Err(misplaced_dot(i))
+}
+
+type Items<I> = (Vec<I>, Option<I>);
+
+fn items<'a, T: Tree<'a>>(tree: &T, dotted: bool, i: &'a str) -> IResult<&'a str, Items<T::Item>, VerboseError<&'a str>> {
+    use sequence::preceded;
+    use complete::char;
+    let (rest, _) = char('(')(i)?;
+    let (rest, items) = multi::many0(
+        preceded(blank, preceded(combinator::not(dot), |j| tree.datum(j))))(rest)?;
+    let (rest, _) = blank(rest)?;
+    let (rest, tail) = match dot(rest) {
+        Ok((after, _)) if dotted && !items.is_empty() => {
+            let (after, tail) = error::context(
+                DOTTED_TAIL,
+                combinator::cut(preceded(blank, |j| tree.datum(j))))(after)?;
+            let (after, _) = blank(after)?;
+            (after, Some(tail))
+        }
+        Ok(_) => return Err(misplaced_dot(rest)),
+        Err(_) => (rest, None),
+    };
+    let (rest, _) = close(i, tail.is_some(), rest)?;
+    Ok((rest, (items, tail)))
+}
+
+// Splices a list tail into its parent, the same as `parse::dotted` does.
+fn dotted<'a, T: Tree<'a>>(mut items: Vec<T::Item>, tail: T::Item) -> T::Node {
+    match T::unpack(tail) {
+        Ok((rest, tail)) => {
+            items.extend(rest);
+            T::list(items, tail)
+        }
+        Err(tail) => T::list(items, Some(Box::new(tail))),
+    }
+}
@@ -0,0 +1,121 @@
+/// Reading a source text into a `cst::Root`.
+///
+/// The text is checked by the plain grammar first, so the tree is only built
+/// from input that is known to be well formed.
+use nom::{
+    character::complete,
+    error::VerboseError,
+};
+
+use crate::{
+    cst::{Node, Root, Token, TokenKind, Trivia},
+    error::Error,
+};
+
+use super::{
+    atom::atom,
+    block_comment,
+    dot,
+    line_comment,
+};
+
+/// Parse a whole source text into a tree that prints back to it exactly.
+pub fn parse(i: &str) -> Result<Root, Error> {
+    super::parse_all(i)?;
+    let mut datums = Vec::new();
+    let mut rest = i;
+    loop {
+        let (leading, after) = trivia(rest);
+        if after.is_empty() {
+            return Ok(Root { datums, trailing: leading });
+        }
+        let (datum, after) = node(leading, after);
+        datums.push(datum);
+        rest = after;
+    }
+}
+
+// The source between `i` and `rest`.
+fn between(i: &str, rest: &str) -> String {
+    i[..i.len() - rest.len()].to_owned()
+}
+
+fn token(kind: TokenKind, text: &str, leading: Vec<Trivia>) -> Token {
+    Token { kind, text: text.to_owned(), leading }
+}
+
+fn trivia<'a>(mut i: &'a str) -> (Vec<Trivia>, &'a str) {
+    let mut trivia = Vec::new();
+    loop {
+        if let Some(rest) = i.strip_prefix("#;") {
+            let (leading, rest) = self::trivia(rest);
+            let (datum, rest) = node(leading, rest);
+            trivia.push(Trivia::DatumComment(Box::new(datum)));
+            i = rest;
+            continue;
+        }
+        let (rest, piece): (_, fn(String) -> Trivia) =
+            if let Ok((rest, _)) = complete::multispace1::<_, VerboseError<&str>>(i) {
+                (rest, Trivia::Whitespace)
+            } else if let Ok((rest, _)) = line_comment(i) {
+                (rest, Trivia::LineComment)
+            } else if let Ok((rest, _)) = block_comment(i) {
+                (rest, Trivia::BlockComment)
+            } else {
+                return (trivia, i);
+            };
+        trivia.push(piece(between(i, rest)));
+        i = rest;
+    }
+}
+
+// The datum at the start of `i`, which must be well formed.
+fn node<'a>(leading: Vec<Trivia>, i: &'a str) -> (Node, &'a str) {
+    const PREFIXES: [(&str, TokenKind); 4] = [
+        (",@", TokenKind::Splice),
+        (",", TokenKind::UnQuote),
+        ("'", TokenKind::Quote),
+        ("`", TokenKind::Quasi),
+    ];
+    if let Some(&(prefix, kind)) = PREFIXES.iter().find(|(p, _)| i.starts_with(p)) {
+        let (datum, rest) = node(Vec::new(), &i[prefix.len()..]);
+        return (Node::Quote(token(kind, prefix, leading), Box::new(datum)), rest);
+    }
+    if i.starts_with('(') || i.starts_with("#(") {
+        return list(leading, i);
+    }
+    let (rest, _) = atom(i).expect("checked by the grammar");
+    (Node::Atom(token(TokenKind::Atom, &between(i, rest), leading)), rest)
+}
+
+fn list<'a>(leading: Vec<Trivia>, i: &'a str) -> (Node, &'a str) {
+    let vector = i.starts_with('#');
+    let (kind, len) = if vector { (TokenKind::VectorOpen, 2) } else { (TokenKind::Open, 1) };
+    let open = token(kind, &i[..len], leading);
+    let mut items = Vec::new();
+    let mut tail = None;
+    let mut rest = &i[len..];
+    loop {
+        let (leading, after) = trivia(rest);
+        if let Some(after) = after.strip_prefix(')') {
+            let close = token(TokenKind::Close, ")", leading);
+            let node = if vector {
+                Node::Vector { open, items, close }
+            } else {
+                Node::List { open, items, tail, close }
+            };
+            return (node, after);
+        }
+        if let Ok((after, _)) = dot(after) {
+            let dot = token(TokenKind::Dot, ".", leading);
+            let (datum_leading, after) = trivia(after);
+            let (datum, after) = node(datum_leading, after);
+            tail = Some((dot, Box::new(datum)));
+            rest = after;
+            continue;
+        }
+        let (item, after) = node(leading, after);
+        items.push(item);
+        rest = after;
+    }
+}
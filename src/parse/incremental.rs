@@ -0,0 +1,195 @@
+/// Parsing input that arrives a piece at a time, like lines typed at a REPL.
+///
+/// Each piece is scanned once, and every datum is parsed as soon as it's
+/// whole, so feeding more text never goes back over what came before.
+use std::mem;
+
+use crate::{
+    error::{Error, ErrorKind},
+    expr::Sexp,
+};
+
+use super::{ParseOptions, Scanner};
+
+/// What the input is waiting on.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Expected {
+    /// `)` to close a list or vector.
+    Paren,
+    /// `"` to close a string.
+    Quote,
+    /// `|` to close a `|symbol|`.
+    Bar,
+    /// `|#` to close a block comment.
+    BlockCommentEnd,
+    /// The datum after a quote prefix or `#;`.
+    Datum,
+}
+
+/// The state of the input after a `feed`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Status {
+    /// Every datum since the last `Complete`, with nothing left open.
+    Complete(Vec<Sexp>),
+    /// The input stops inside a datum, nested `depth` lists deep.
+    Incomplete { depth: usize, expected: Expected },
+}
+
+/// A parser that can be fed more text until its input is complete.
+///
+/// Text is taken to be fed in whole lines, or at least whole tokens, so an
+/// atom at the very end of a `feed` is finished. Input that goes past the
+/// `ParseOptions` limits is an error, as soon as it's fed.
+#[derive(Debug, Clone)]
+pub struct Incremental {
+    // Everything fed since the last `Complete`
+    text: String,
+    // Where the datums parsed so far end in `text`
+    start: usize,
+    scanner: Scanner,
+    datums: Vec<Sexp>,
+    options: ParseOptions,
+}
+
+impl Default for Incremental {
+    fn default() -> Incremental {
+        Incremental::new()
+    }
+}
+
+impl Incremental {
+    pub fn new() -> Incremental {
+        Incremental::with_options(ParseOptions::default())
+    }
+
+    /// A parser that keeps to the `options` limits instead of the default ones.
+    pub fn with_options(options: ParseOptions) -> Incremental {
+        Incremental {
+            text: String::new(),
+            start: 0,
+            scanner: Scanner::with_options(options),
+            datums: Vec::new(),
+            options,
+        }
+    }
+
+    /// Add `text` to the input and report whether it's complete.
+    ///
+    /// An error drops all of the pending input, and its position is counted
+    /// from the start of that input.
+    pub fn feed(&mut self, text: &str) -> Result<Status, Error> {
+        self.text.push_str(text);
+        while let Some(end) = self.scanner.scan(&self.text[self.start..]) {
+            self.take(end)?;
+        }
+        if let Some(end) = self.scanner.end_input(&self.text[self.start..]) {
+            self.take(end)?;
+        }
+        match self.scanner.expected() {
+            Some(expected) => Ok(Status::Incomplete { depth: self.scanner.depth(), expected }),
+            None => {
+                let datums = mem::take(&mut self.datums);
+                self.reset();
+                Ok(Status::Complete(datums))
+            }
+        }
+    }
+
+    /// Drop any pending input.
+    pub fn reset(&mut self) {
+        *self = Incremental::with_options(self.options);
+    }
+
+    // Parse the datum that ends `end` bytes after `start`.
+    fn take(&mut self, end: usize) -> Result<(), Error> {
+        let slice = &self.text[self.start..self.start + end];
+        let parsed = match self.scanner.exceeded() {
+            Some((limit, max, at)) => Err(Error::at(slice, at, ErrorKind::LimitExceeded { limit, max })),
+            None => super::parse_unchecked(slice),
+        };
+        match parsed {
+            Ok(sexp) => {
+                self.datums.push(sexp);
+                self.start += end;
+                self.scanner.reset();
+                Ok(())
+            }
+            Err(e) => {
+                let before = &self.text[..self.start];
+                let line_start = &before[before.rfind('\n').map_or(0, |n| n + 1)..];
+                let e = e.shifted(self.start, before.matches('\n').count() + 1, line_start);
+                self.reset();
+                Err(e)
+            }
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_lines(lines: &[&str]) -> Vec<Result<Status, Error>> {
+        let mut parser = Incremental::new();
+        lines.iter().map(|line| parser.feed(line)).collect()
+    }
+
+    fn incomplete(depth: usize, expected: Expected) -> Result<Status, Error> {
+        Ok(Status::Incomplete { depth, expected })
+    }
+
+    #[test]
+    fn complete_lines() {
+        assert_eq!(feed_lines(&["(a b) c\n"]), vec![Ok(Status::Complete(vec![
+            "(a b)".parse().unwrap(),
+            "c".parse().unwrap(),
+        ]))]);
+        assert_eq!(feed_lines(&["  ; nothing\n"]), vec![Ok(Status::Complete(vec![]))]);
+        // A last atom without a newline is still whole
+        assert_eq!(feed_lines(&["'x"]), vec![Ok(Status::Complete(vec!["'x".parse().unwrap()]))]);
+    }
+
+    #[test]
+    fn waits_for_more() {
+        assert_eq!(
+            feed_lines(&["(define (f x)\n", "  (g \"a\n", "b\")\n", "  #| note\n", "|# x)\n"]),
+            vec![
+                incomplete(1, Expected::Paren),
+                incomplete(2, Expected::Quote),
+                incomplete(1, Expected::Paren),
+                incomplete(1, Expected::BlockCommentEnd),
+                Ok(Status::Complete(vec!["(define (f x) (g \"a\nb\") x)".parse().unwrap()])),
+            ]
+        );
+        assert_eq!(
+            feed_lines(&["(a) #;\n", "b c\n"]),
+            vec![
+                incomplete(0, Expected::Datum),
+                Ok(Status::Complete(vec!["(a)".parse().unwrap(), "c".parse().unwrap()])),
+            ]
+        );
+    }
+
+    #[test]
+    fn datums_are_parsed_once() {
+        let mut parser = Incremental::new();
+        assert_eq!(parser.feed("(a) (b\n"), incomplete(1, Expected::Paren));
+        assert_eq!(parser.datums, vec!["(a)".parse().unwrap()]);
+        assert_eq!(parser.start, 3);
+        assert_eq!(parser.feed(")\n"), Ok(Status::Complete(vec![
+            "(a)".parse().unwrap(),
+            "(b)".parse().unwrap(),
+        ])));
+    }
+
+    #[test]
+    fn errors_drop_the_input() {
+        let mut parser = Incremental::new();
+        assert_eq!(parser.feed("(a\n"), incomplete(1, Expected::Paren));
+        let err = parser.feed("  ,1)\n").unwrap_err();
+        assert_eq!((err.kind(), err.line(), err.column()), (&ErrorKind::UnquoteLiteral, 2, 5));
+        assert_eq!(parser.feed("b\n"), Ok(Status::Complete(vec!["b".parse().unwrap()])));
+    }
+}
@@ -0,0 +1,53 @@
+/// Reading into `intern::Sexp`, with every symbol interned in a caller's
+/// `SymbolTable`.
+///
+/// This reads with the borrowed parser first, so names are only copied the
+/// first time the table sees them.
+use crate::{
+    borrowed,
+    error::Error,
+    intern::{Atom, Quote, Sexp, SymbolTable},
+};
+
+/// Parse a source text that holds exactly one datum.
+pub fn parse(i: &str, table: &mut SymbolTable) -> Result<Sexp, Error> {
+    super::borrowed::parse(i).map(|sexp| intern(sexp, table))
+}
+
+/// Parse a whole source text into its top level datums.
+///
+/// Call it once per file with the same `table` to share symbols between them.
+pub fn parse_all(i: &str, table: &mut SymbolTable) -> Result<Vec<Sexp>, Error> {
+    super::borrowed::parse_all(i).map(|sexps| {
+        sexps.into_iter().map(|sexp| intern(sexp, table)).collect()
+    })
+}
+
+fn intern<'a>(sexp: borrowed::Sexp<'a>, table: &mut SymbolTable) -> Sexp {
+    let mut boxed = |s: Box<borrowed::Sexp<'a>>| Box::new(intern(*s, table));
+    match sexp {
+        borrowed::Sexp::Quote(q) => Sexp::Quote(match q {
+            borrowed::Quote::Quote(s) => Quote::Quote(boxed(s)),
+            borrowed::Quote::Quasi(s) => Quote::Quasi(boxed(s)),
+            borrowed::Quote::UnQuote(s) => Quote::UnQuote(boxed(s)),
+            borrowed::Quote::Splice(s) => Quote::Splice(boxed(s)),
+        }),
+        borrowed::Sexp::Constant(a) => Sexp::Constant(match a {
+            borrowed::Atom::Num(n) => Atom::Num(n),
+            borrowed::Atom::Bool(b) => Atom::Bool(b),
+            borrowed::Atom::Char(c) => Atom::Char(c),
+            borrowed::Atom::Symbol(s) => Atom::Symbol(table.intern(&s)),
+            borrowed::Atom::String(s) => Atom::String(s.into_owned()),
+        }),
+        borrowed::Sexp::List(items) => Sexp::List(intern_all(items, table)),
+        borrowed::Sexp::DottedList(items, tail) => {
+            let items = intern_all(items, table);
+            Sexp::DottedList(items, Box::new(intern(*tail, table)))
+        }
+        borrowed::Sexp::Vector(items) => Sexp::Vector(intern_all(items, table)),
+    }
+}
+
+fn intern_all<'a>(items: Vec<borrowed::Sexp<'a>>, table: &mut SymbolTable) -> Vec<Sexp> {
+    items.into_iter().map(|sexp| intern(sexp, table)).collect()
+}
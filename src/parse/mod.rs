@@ -4,45 +4,271 @@ use nom::{
     branch,
     multi,
     combinator,
-    error::{ self, VerboseError },
+    error::{ self, VerboseError, VerboseErrorKind },
     sequence,
     character::complete,
 };
 
-use crate::expr::Sexp;
+use crate::{
+    expr::Sexp,
+    error::{
+        Error,
+        ErrorKind,
+        BLOCK_COMMENT,
+        CLOSING_PAREN,
+        COMMENTED_DATUM,
+        DOTTED_TAIL,
+        END_OF_INPUT,
+        OPENING_PAREN,
+    },
+};
 
 pub mod quote;
-use quote::quote;
 
 pub mod atom;
-use atom::atom;
 
+pub mod spanned;
+
+pub mod borrowed;
+
+pub mod interned;
+
+pub mod cst;
+
+pub mod incremental;
+
+mod compound;
+
+mod scan;
+pub(crate) use scan::Scanner;
+
+/// `borrowed::sexp`, with the result copied out of the source.
 pub fn sexp<'a>(i: &'a str) -> IResult<&'a str, Sexp, VerboseError<&'a str>> {
-    use combinator::map;
-    branch::alt((
-        map(quote, Sexp::Quote),
-        map(list, Sexp::List),
-        map(vector, Sexp::Vector),
-        // `atom` is very greedy, so it needs to come last
-        map(atom, Sexp::Constant),
-    ))(i)
-}
-
-fn list<'a>(i: &'a str) -> IResult<&'a str, Vec<Sexp>, VerboseError<&'a str>> {
-    use sequence::{preceded, delimited};
-    use complete::{char, multispace0};
-    delimited(
-        char('('),
-        multi::many0(preceded(multispace0, sexp)),
-        error::context(
-            "closing paren",
-            combinator::cut(
-                preceded(
-                    multispace0,
-                    char(')'))))
+    combinator::map(borrowed::sexp, crate::borrowed::Sexp::into_owned)(i)
+}
+
+/// Parse every top level datum in `i`, failing if anything but whitespace is left over.
+pub fn program<'a>(i: &'a str) -> IResult<&'a str, Vec<Sexp>, VerboseError<&'a str>> {
+    use sequence::{preceded, terminated};
+    terminated(
+        multi::many0(preceded(blank, sexp)),
+        preceded(
+            blank,
+            error::context(END_OF_INPUT, combinator::eof)),
+    )(i)
+}
+
+/// Parse a source text that holds exactly one datum, within the default
+/// `ParseOptions`.
+pub fn parse(i: &str) -> Result<Sexp, Error> {
+    parse_with(i, &ParseOptions::default())
+}
+
+/// Parse a whole source text into its top level datums, within the default
+/// `ParseOptions`.
+pub fn parse_all(i: &str) -> Result<Vec<Sexp>, Error> {
+    parse_all_with(i, &ParseOptions::default())
+}
+
+// `parse` for text that a `Scanner` has already checked the limits of.
+pub(crate) fn parse_unchecked(i: &str) -> Result<Sexp, Error> {
+    use sequence::{delimited, terminated};
+    terminated(
+        delimited(blank, sexp, blank),
+        error::context(END_OF_INPUT, combinator::eof),
     )(i)
+        .map(|(_, sexp)| sexp)
+        .map_err(|e| Error::from_nom(i, e))
 }
 
-fn vector<'a>(i: &'a str) -> IResult<&'a str, Vec<Sexp>, VerboseError<&'a str>> {
-    sequence::preceded(complete::char('#'), list)(i)
+fn parse_all_unchecked(i: &str) -> Result<Vec<Sexp>, Error> {
+    program(i)
+        .map(|(_, sexps)| sexps)
+        .map_err(|e| Error::from_nom(i, e))
+}
+
+/// Limits on what a parse will build, for input that can't be trusted.
+///
+/// The grammar recurses once per level of nesting, so `max_depth` is what
+/// keeps deeply nested input from overflowing the stack. Lists, vectors,
+/// quotes and `#;` comments each count as a level.
+///
+/// Every `parse` and `parse_all` in `rexp::parse`, and the `Reader` and
+/// `Incremental` parsers, keep to the default limits unless given others.
+/// Only the nom parsers like `sexp` and `program` don't check them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ParseOptions {
+    pub max_depth: usize,
+    /// In bytes of source, including any quotes, bars and escapes.
+    pub max_atom_len: usize,
+    /// Items in any one list or vector.
+    pub max_list_len: usize,
+    /// Datums in the whole input, nested and commented out ones included.
+    pub max_total_nodes: usize,
+}
+
+impl Default for ParseOptions {
+    /// Only the depth is limited, to one that fits a 2 MiB thread stack
+    /// even in a debug build.
+    fn default() -> ParseOptions {
+        ParseOptions {
+            max_depth: 128,
+            max_atom_len: usize::MAX,
+            max_list_len: usize::MAX,
+            max_total_nodes: usize::MAX,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Check `i` against the limits without parsing it, so that any of the
+    /// parsers can then read it safely.
+    pub fn check(&self, i: &str) -> Result<(), Error> {
+        let mut scanner = Scanner::with_options(*self);
+        let mut base = 0;
+        loop {
+            let end = scanner.scan(&i[base..]).or_else(|| scanner.end_input(&i[base..]));
+            if let Some((limit, max, at)) = scanner.exceeded() {
+                return Err(Error::at(i, base + at, ErrorKind::LimitExceeded { limit, max }));
+            }
+            match end {
+                Some(end) => {
+                    base += end;
+                    scanner.reset();
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+/// `parse`, with the `options` limits in place of the default ones.
+pub fn parse_with(i: &str, options: &ParseOptions) -> Result<Sexp, Error> {
+    options.check(i)?;
+    parse_unchecked(i)
+}
+
+/// `parse_all`, with the `options` limits in place of the default ones.
+pub fn parse_all_with(i: &str, options: &ParseOptions) -> Result<Vec<Sexp>, Error> {
+    options.check(i)?;
+    parse_all_unchecked(i)
+}
+
+/// `(items . tail)` the way the parser reads it: `(a . (b c))` is the same
+/// list as `(a b c)`, so the dot is only kept when `tail` isn't a list itself.
+pub fn dotted(mut items: Vec<Sexp>, tail: Sexp) -> Sexp {
+    match tail {
+        Sexp::List(rest) => {
+            items.extend(rest);
+            Sexp::List(items)
+        }
+        Sexp::DottedList(rest, tail) => {
+            items.extend(rest);
+            Sexp::DottedList(items, tail)
+        }
+        tail => Sexp::DottedList(items, Box::new(tail)),
+    }
+}
+
+/// The `)` of a list that starts at `open`, with `dotted` when a tail was read
+/// after a `.`. A missing one is reported at `open`, which is more use than
+/// wherever the input happened to stop.
+pub(crate) fn close<'a>(open: &'a str, dotted: bool, i: &'a str) -> IResult<&'a str, char, VerboseError<&'a str>> {
+    use combinator::cut;
+    use complete::char;
+    // Exactly one datum may follow the dot
+    if dotted && !i.is_empty() {
+        return error::context(DOTTED_TAIL, cut(char(')')))(i);
+    }
+    error::context(CLOSING_PAREN, cut(char(')')))(i).map_err(|e| e.map(|mut e: VerboseError<&'a str>| {
+        e.errors.push((open, VerboseErrorKind::Context(OPENING_PAREN)));
+        e
+    }))
+}
+
+/// A lone `.` token, as opposed to symbols like `...` or numbers like `.5`.
+pub(crate) fn dot<'a>(i: &'a str) -> IResult<&'a str, &'a str, VerboseError<&'a str>> {
+    use nom::bytes::complete::tag;
+    use combinator::{recognize, verify};
+    verify(
+        recognize(sequence::pair(tag("."), atom::token)),
+        |t: &str| t == ".",
+    )(i)
+}
+
+// A `.` anywhere but before the last datum of a list.
+pub(crate) fn misplaced_dot<'a>(i: &'a str) -> nom::Err<VerboseError<&'a str>> {
+    bounce(i, DOTTED_TAIL)
+}
+
+/// A failure at `i` that no other branch can recover from.
+pub(crate) fn bounce<'a>(i: &'a str, msg: &'static str) -> nom::Err<VerboseError<&'a str>> {
+    nom::Err::Failure(VerboseError {
+        errors: vec![(i, VerboseErrorKind::Context(msg))],
+    })
+}
+
+
+// Whitespace and comments
+
+/// Skip any run of whitespace, `;` line comments, `#| ... |#` block comments
+/// and `#;` datum comments, since the reader treats all of them as whitespace.
+pub(crate) fn blank<'a>(i: &'a str) -> IResult<&'a str, (), VerboseError<&'a str>> {
+    use combinator::value;
+    value(
+        (),
+        multi::many0(branch::alt((
+            value((), complete::multispace1),
+            line_comment,
+            block_comment,
+            datum_comment,
+        ))),
+    )(i)
+}
+
+pub(crate) fn line_comment<'a>(i: &'a str) -> IResult<&'a str, (), VerboseError<&'a str>> {
+    use nom::bytes::complete::take_till;
+    combinator::value(
+        (),
+        sequence::preceded(complete::char(';'), take_till(|c| c == '\n')),
+    )(i)
+}
+
+// Block comments nest, so `#| a #| b |# c |#` is a single comment.
+pub(crate) fn block_comment<'a>(i: &'a str) -> IResult<&'a str, (), VerboseError<&'a str>> {
+    use nom::bytes::complete::tag;
+    let (rest, _) = tag("#|")(i)?;
+    let mut depth = 1;
+    let mut j = 0;
+    while j < rest.len() {
+        if rest[j..].starts_with("|#") {
+            depth -= 1;
+            j += 2;
+            if depth == 0 {
+                return Ok((&rest[j..], ()));
+            }
+        } else if rest[j..].starts_with("#|") {
+            depth += 1;
+            j += 2;
+        } else {
+            j += rest[j..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    Err(nom::Err::Failure(VerboseError {
+        errors: vec![(i, VerboseErrorKind::Context(BLOCK_COMMENT))],
+    }))
+}
+
+// `#;` comments out the whole datum that follows it.
+fn datum_comment<'a>(i: &'a str) -> IResult<&'a str, (), VerboseError<&'a str>> {
+    use nom::bytes::complete::tag;
+    use sequence::{preceded, tuple};
+    combinator::value(
+        (),
+        preceded(
+            tag("#;"),
+            error::context(
+                COMMENTED_DATUM,
+                combinator::cut(tuple((blank, borrowed::sexp))))),
+    )(i)
 }
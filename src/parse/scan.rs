@@ -0,0 +1,388 @@
+/// Finds where each top level datum ends without building it.
+///
+/// The streaming and incremental parsers feed text through a `Scanner` as it
+/// arrives and only hand a datum to the real grammar once it's whole. The
+/// scanner keeps its state between calls, so nothing is scanned twice.
+/// Anything it doesn't understand ends the datum early, which leaves the
+/// grammar to report the error.
+use crate::error::Limit;
+
+use super::{atom::is_delimiter, incremental::Expected, ParseOptions};
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Scanner {
+    // Bytes of the pending text already scanned
+    pos: usize,
+    mode: Mode,
+    frames: Vec<Frame>,
+    // Only set when checking limits, which then count across datums
+    options: Option<ParseOptions>,
+    nodes: usize,
+    exceeded: Option<(Limit, usize, usize)>,
+}
+
+// What the datums being scanned are nested in.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Frame {
+    /// A list or vector, waiting on `)`, with the items it has so far.
+    List(usize),
+    /// A quote prefix, waiting on its datum.
+    Quote,
+    /// A `#;` comment, waiting on the datum it drops.
+    Comment,
+}
+
+// Where in the lexical syntax the scanner is.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+enum Mode {
+    #[default]
+    Blank,
+    LineComment,
+    BlockComment { depth: usize, last: Option<char> },
+    // Just after a `#` outside of a token
+    Hash,
+    // Just after `#\`, where the next char is taken as is
+    CharStart,
+    Token { start: usize },
+    // A `#` inside a token, which could start a comment
+    TokenHash { start: usize, hash: usize },
+    // Just after `,`, which could be `,@`
+    Comma,
+    String { start: usize, escape: bool },
+    Bar { start: usize, escape: bool },
+}
+
+impl Scanner {
+    /// A scanner that stops where `options` is exceeded.
+    pub(crate) fn with_options(options: ParseOptions) -> Scanner {
+        Scanner { options: Some(options), ..Scanner::default() }
+    }
+
+    /// The limit that stopped the scan, its maximum, and where it was exceeded.
+    pub(crate) fn exceeded(&self) -> Option<(Limit, usize, usize)> {
+        self.exceeded
+    }
+
+    /// Scan what's new in `text`, the same text as last time with more on the
+    /// end, and return the end of its first datum once it has one.
+    pub(crate) fn scan(&mut self, text: &str) -> Option<usize> {
+        while let Some(c) = text[self.pos..].chars().next() {
+            let at = self.pos;
+            self.pos += c.len_utf8();
+            if let Some(end) = self.step(text, at, c) {
+                return Some(end);
+            }
+        }
+        None
+    }
+
+    /// Start over on the text after the datum `scan` found.
+    pub(crate) fn reset(&mut self) {
+        *self = Scanner {
+            options: self.options,
+            nodes: self.nodes,
+            ..Scanner::default()
+        };
+    }
+
+    /// Whether everything scanned so far is whitespace and comments.
+    pub(crate) fn is_blank(&self) -> bool {
+        self.frames.is_empty() && matches!(self.mode, Mode::Blank | Mode::LineComment)
+    }
+
+    /// End a token that runs up to the end of `text`, as if a delimiter
+    /// followed it, and return the end of the datum if that finished one.
+    pub(crate) fn end_input(&mut self, text: &str) -> Option<usize> {
+        let start = match self.mode {
+            Mode::Token { start } | Mode::TokenHash { start, .. } => start,
+            Mode::Hash => text.len() - 1,
+            Mode::CharStart => text.len() - 2,
+            _ => return None,
+        };
+        self.mode = Mode::Blank;
+        self.end_token(text, start, text.len())
+    }
+
+    /// What would close the innermost thing still open, if anything is.
+    pub(crate) fn expected(&self) -> Option<Expected> {
+        match self.mode {
+            Mode::String { .. } => Some(Expected::Quote),
+            Mode::Bar { .. } => Some(Expected::Bar),
+            Mode::BlockComment { .. } => Some(Expected::BlockCommentEnd),
+            _ => match self.frames.last()? {
+                Frame::List(_) => Some(Expected::Paren),
+                Frame::Quote | Frame::Comment => Some(Expected::Datum),
+            },
+        }
+    }
+
+    /// How many lists and vectors are open.
+    pub(crate) fn depth(&self) -> usize {
+        self.frames.iter().filter(|f| matches!(f, Frame::List(_))).count()
+    }
+
+    // `c` is at `at`, and `self.pos` is already past it.
+    fn step(&mut self, text: &str, at: usize, c: char) -> Option<usize> {
+        match self.mode {
+            Mode::Blank => match c {
+                ';' => self.mode = Mode::LineComment,
+                '#' => self.mode = Mode::Hash,
+                '"' => self.mode = Mode::String { start: at, escape: false },
+                '|' => self.mode = Mode::Bar { start: at, escape: false },
+                '(' => return self.push(Frame::List(0), at),
+                ')' => match self.frames.last() {
+                    Some(Frame::List(_)) => {
+                        self.frames.pop();
+                        return self.finish(self.pos);
+                    }
+                    // A missing datum or a stray paren
+                    _ => return Some(self.pos),
+                },
+                '\'' | '`' => return self.push(Frame::Quote, at),
+                ',' => {
+                    self.mode = Mode::Comma;
+                    return self.push(Frame::Quote, at);
+                }
+                c if c.is_whitespace() => {}
+                // Brackets don't start anything
+                c if is_delimiter(c) => return Some(self.pos),
+                _ => self.mode = Mode::Token { start: at },
+            },
+            Mode::LineComment => {
+                if c == '\n' {
+                    self.mode = Mode::Blank;
+                }
+            }
+            Mode::BlockComment { depth, last } => {
+                self.mode = match (last, c) {
+                    (Some('|'), '#') if depth == 1 => Mode::Blank,
+                    (Some('|'), '#') => Mode::BlockComment { depth: depth - 1, last: None },
+                    (Some('#'), '|') => Mode::BlockComment { depth: depth + 1, last: None },
+                    _ => Mode::BlockComment { depth, last: Some(c) },
+                }
+            }
+            Mode::Hash => match c {
+                '|' => self.mode = Mode::BlockComment { depth: 1, last: None },
+                ';' => {
+                    self.mode = Mode::Blank;
+                    return self.push(Frame::Comment, at - 1);
+                }
+                '(' => {
+                    self.mode = Mode::Blank;
+                    return self.push(Frame::List(0), at - 1);
+                }
+                '\\' => self.mode = Mode::CharStart,
+                _ => return self.token(text, at - 1, at, c),
+            },
+            Mode::CharStart => self.mode = Mode::Token { start: at - 2 },
+            Mode::Token { start } => return self.token(text, start, at, c),
+            Mode::TokenHash { start, hash } => match c {
+                // `a#|...|#` is `a` and a comment, so go back to the `#`
+                '|' | ';' => {
+                    self.pos = hash;
+                    self.mode = Mode::Blank;
+                    return self.end_token(text, start, hash);
+                }
+                _ => return self.token(text, start, at, c),
+            },
+            Mode::Comma => {
+                self.mode = Mode::Blank;
+                if c != '@' {
+                    return self.step(text, at, c);
+                }
+            }
+            Mode::String { start, escape } | Mode::Bar { start, escape } => {
+                let end = if let Mode::String { .. } = self.mode { '"' } else { '|' };
+                match c {
+                    _ if escape => self.set_escape(false),
+                    '\\' => self.set_escape(true),
+                    c if c == end => {
+                        self.mode = Mode::Blank;
+                        if self.pos - start > self.limit(|o| o.max_atom_len) {
+                            return self.exceed(Limit::AtomLength, |o| o.max_atom_len, start);
+                        }
+                        return self.finish(self.pos);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+
+    // `c` at `at` inside a token that began at `start`.
+    fn token(&mut self, text: &str, start: usize, at: usize, c: char) -> Option<usize> {
+        if c == '#' {
+            self.mode = Mode::TokenHash { start, hash: at };
+            None
+        } else if is_delimiter(c) {
+            // The delimiter belongs to whatever comes next
+            self.pos = at;
+            self.mode = Mode::Blank;
+            self.end_token(text, start, at)
+        } else {
+            self.mode = Mode::Token { start };
+            None
+        }
+    }
+
+    fn end_token(&mut self, text: &str, start: usize, end: usize) -> Option<usize> {
+        match self.frames.last() {
+            // The `.` of a dotted list isn't a datum of its own
+            Some(Frame::List(_)) if &text[start..end] == "." => None,
+            _ if end - start > self.limit(|o| o.max_atom_len) => {
+                self.exceed(Limit::AtomLength, |o| o.max_atom_len, start)
+            }
+            _ => self.finish(end),
+        }
+    }
+
+    fn set_escape(&mut self, escape: bool) {
+        self.mode = match self.mode {
+            Mode::String { start, .. } => Mode::String { start, escape },
+            Mode::Bar { start, .. } => Mode::Bar { start, escape },
+            mode => mode,
+        };
+    }
+
+    fn push(&mut self, frame: Frame, at: usize) -> Option<usize> {
+        self.frames.push(frame);
+        if self.frames.len() > self.limit(|o| o.max_depth) {
+            return self.exceed(Limit::Depth, |o| o.max_depth, at);
+        }
+        None
+    }
+
+    fn limit(&self, max: impl Fn(&ParseOptions) -> usize) -> usize {
+        self.options.as_ref().map_or(usize::MAX, max)
+    }
+
+    // Stop the scan at `at`.
+    fn exceed(&mut self, limit: Limit, max: impl Fn(&ParseOptions) -> usize, at: usize) -> Option<usize> {
+        self.exceeded = Some((limit, self.limit(max), at));
+        Some(at)
+    }
+
+    // A datum ended at `end`, so settle everything that was waiting on one.
+    fn finish(&mut self, end: usize) -> Option<usize> {
+        loop {
+            self.nodes += 1;
+            if self.nodes > self.limit(|o| o.max_total_nodes) {
+                return self.exceed(Limit::TotalNodes, |o| o.max_total_nodes, end);
+            }
+            let max_items = self.limit(|o| o.max_list_len);
+            match self.frames.last_mut() {
+                None => return Some(end),
+                Some(Frame::List(items)) => {
+                    *items += 1;
+                    if *items > max_items {
+                        return self.exceed(Limit::ListLength, |o| o.max_list_len, end);
+                    }
+                    return None;
+                }
+                Some(Frame::Quote) => {
+                    self.frames.pop();
+                }
+                // The dropped datum counts as whitespace
+                Some(Frame::Comment) => {
+                    self.frames.pop();
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The end of every datum `text` splits into, scanning it in one go.
+    fn ends(text: &str) -> Vec<usize> {
+        let mut scanner = Scanner::default();
+        let mut base = 0;
+        let mut ends = vec![];
+        while let Some(end) = scanner.scan(&text[base..]) {
+            base += end;
+            ends.push(base);
+            scanner.reset();
+        }
+        ends
+    }
+
+    #[test]
+    fn datum_ends() {
+        assert_eq!(ends("(a b) (c)"), vec![5, 9]);
+        assert_eq!(ends("\"a)\" |b c|"), vec![4, 10]);
+        assert_eq!(ends("'(a . b) `,@c "), vec![8, 13]);
+        assert_eq!(ends("#(1 2) #\\) x"), vec![6, 10]);
+    }
+
+    #[test]
+    fn tokens_end_at_delimiters() {
+        // The last token could still be going
+        assert_eq!(ends("abc def"), vec![3]);
+        assert_eq!(ends("a\"b\""), vec![1, 4]);
+        assert_eq!(ends("a#|c|# b;c\n"), vec![1, 8]);
+        assert_eq!(ends("a#b "), vec![3]);
+    }
+
+    #[test]
+    fn comments_are_not_datums() {
+        assert_eq!(ends("; (a\n#| (b |# #; (c) #;#;d e f "), vec![30]);
+        let mut scanner = Scanner::default();
+        assert_eq!(scanner.scan("#| #| |# |# ; x"), None);
+        assert!(scanner.is_blank());
+    }
+
+    #[test]
+    fn escapes_dont_close() {
+        assert_eq!(ends(r#""a\"b" |c\|d|"#), vec![6, 13]);
+    }
+
+    #[test]
+    fn resumes_where_it_left_off() {
+        let text = "(define (f x)\n  \"a \\\"string\\\"\" #| c |# x)";
+        for split in 1..text.len() {
+            let mut scanner = Scanner::default();
+            assert_eq!(scanner.scan(&text[..split]), None, "{}", split);
+            assert_eq!(scanner.scan(text), Some(text.len()), "{}", split);
+        }
+    }
+
+    #[test]
+    fn what_is_still_open() {
+        let open = |text: &str| {
+            let mut scanner = Scanner::default();
+            assert_eq!(scanner.scan(text), None, "{}", text);
+            (scanner.depth(), scanner.expected())
+        };
+        assert_eq!(open("(a (b"), (2, Some(Expected::Paren)));
+        assert_eq!(open("(a \"b"), (1, Some(Expected::Quote)));
+        assert_eq!(open("#(|a"), (1, Some(Expected::Bar)));
+        assert_eq!(open("#| #| |#"), (0, Some(Expected::BlockCommentEnd)));
+        assert_eq!(open("(a ,@"), (1, Some(Expected::Datum)));
+        assert_eq!(open("#;"), (0, Some(Expected::Datum)));
+        assert_eq!(open("; (a"), (0, None));
+    }
+
+    #[test]
+    fn end_input_ends_tokens() {
+        let mut scanner = Scanner::default();
+        assert_eq!(scanner.scan("'abc"), None);
+        assert_eq!(scanner.end_input("'abc"), Some(4));
+        let mut scanner = Scanner::default();
+        assert_eq!(scanner.scan("(a b"), None);
+        assert_eq!(scanner.end_input("(a b"), None);
+        assert_eq!(scanner.expected(), Some(Expected::Paren));
+    }
+
+    #[test]
+    fn errors_end_the_datum() {
+        assert_eq!(ends(") a"), vec![1]);
+        assert_eq!(ends("(a ') b"), vec![5]);
+        assert_eq!(ends(". a"), vec![1]);
+    }
+}
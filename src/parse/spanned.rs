@@ -0,0 +1,116 @@
+/// The same grammar as `parse::sexp`, but every node records its `Span`.
+///
+/// This lives apart from the plain parser so that reading without spans
+/// doesn't pay for them.
+use nom::{
+    IResult,
+    branch,
+    multi,
+    combinator,
+    error::{ self, VerboseError },
+    sequence,
+};
+
+use crate::{
+    expr::Atom,
+    error::{ Error, END_OF_INPUT },
+    span::{
+        Span,
+        Spanned,
+        SpannedQuote,
+        SpannedSexp,
+    },
+};
+
+use super::{
+    atom::atom,
+    blank,
+    compound::{ self, Parts, Tree },
+    ParseOptions,
+};
+
+/// Parse a single datum, with spans counted from the start of `i`.
+pub fn sexp<'a>(i: &'a str) -> IResult<&'a str, Spanned<SpannedSexp>, VerboseError<&'a str>> {
+    node(i, i)
+}
+
+/// Parse every top level datum in `i`, failing if anything but whitespace is left over.
+pub fn program<'a>(i: &'a str) -> IResult<&'a str, Vec<Spanned<SpannedSexp>>, VerboseError<&'a str>> {
+    use sequence::{preceded, terminated};
+    terminated(
+        multi::many0(preceded(blank, |j| node(i, j))),
+        preceded(
+            blank,
+            error::context(END_OF_INPUT, combinator::eof)),
+    )(i)
+}
+
+/// Parse a whole source text into its top level datums, within the default
+/// `ParseOptions`.
+pub fn parse_all(i: &str) -> Result<Vec<Spanned<SpannedSexp>>, Error> {
+    ParseOptions::default().check(i)?;
+    program(i)
+        .map(|(_, sexps)| sexps)
+        .map_err(|e| Error::from_nom(i, e))
+}
+
+// Offsets are measured from `base`, which every remaining input is a suffix of.
+fn span<'a>(base: &'a str, from: &'a str, to: &'a str) -> Span {
+    Span::new(base.len() - from.len(), base.len() - to.len())
+}
+
+fn node<'a>(base: &'a str, i: &'a str) -> IResult<&'a str, Spanned<SpannedSexp>, VerboseError<&'a str>> {
+    use combinator::map;
+    let nodes = Nodes(base);
+    let (rest, sexp) = branch::alt((
+        map(|j| compound::quote(&nodes, j), SpannedSexp::Quote),
+        |j| compound::list(&nodes, j),
+        map(|j| compound::vector(&nodes, j), SpannedSexp::Vector),
+        compound::stray_dot,
+        // `atom` is very greedy, so it needs to come last
+        map(atom, SpannedSexp::Constant),
+    ))(i)?;
+    Ok((rest, Spanned::new(sexp, span(base, i, rest))))
+}
+
+// Nodes with offsets counted from the text they were read from.
+struct Nodes<'a>(&'a str);
+
+impl<'a> Tree<'a> for Nodes<'a> {
+    type Item = Spanned<SpannedSexp>;
+    type Node = SpannedSexp;
+    type Quote = SpannedQuote;
+
+    fn datum(&self, i: &'a str) -> IResult<&'a str, Spanned<SpannedSexp>, VerboseError<&'a str>> {
+        node(self.0, i)
+    }
+
+    fn is_literal(item: &Spanned<SpannedSexp>) -> bool {
+        matches!(&item.node, SpannedSexp::Constant(a) if !matches!(a, Atom::Symbol(_)))
+    }
+
+    fn quote(prefix: &str, quoted: Box<Spanned<SpannedSexp>>) -> SpannedQuote {
+        match prefix {
+            "'" => SpannedQuote::Quote(quoted),
+            "`" => SpannedQuote::Quasi(quoted),
+            "," => SpannedQuote::UnQuote(quoted),
+            _ => SpannedQuote::Splice(quoted),
+        }
+    }
+
+    fn list(items: Vec<Spanned<SpannedSexp>>, tail: Option<Box<Spanned<SpannedSexp>>>) -> SpannedSexp {
+        match tail {
+            Some(tail) => SpannedSexp::DottedList(items, tail),
+            None => SpannedSexp::List(items),
+        }
+    }
+
+    fn unpack(item: Spanned<SpannedSexp>) -> Result<Parts<Spanned<SpannedSexp>>, Spanned<SpannedSexp>> {
+        match item.node {
+            SpannedSexp::List(items) => Ok((items, None)),
+            SpannedSexp::DottedList(items, tail) => Ok((items, Some(tail))),
+            // A tail that isn't a list keeps its own span
+            node => Err(Spanned::new(node, item.span)),
+        }
+    }
+}
@@ -0,0 +1,638 @@
+/// A width aware pretty printer for `Sexp`.
+///
+/// Layout goes through a small Wadler style document algebra: a `Doc` is
+/// text plus `Line`s, and each `Group` is printed flat if it fits in the
+/// remaining width or with all of its own `Line`s broken if not.
+use std::collections::HashMap;
+
+use crate::{
+    cst::{Node, Trivia},
+    error::Error,
+    expr::{Atom, Quote, Sexp},
+};
+
+/// A document to be laid out.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Doc {
+    Nil,
+    Text(String),
+    /// A space when its group is flat, otherwise a newline and the indent.
+    Line,
+    /// Always a newline and the indent, so its groups can never be flat.
+    HardLine,
+    Concat(Vec<Doc>),
+    /// Indent the lines inside by this many more columns.
+    Nest(usize, Box<Doc>),
+    /// Indent the lines inside to the column this starts at.
+    Align(Box<Doc>),
+    /// Print flat if it fits, otherwise break its lines.
+    Group(Box<Doc>),
+}
+
+impl Doc {
+    pub fn text(s: impl Into<String>) -> Doc {
+        Doc::Text(s.into())
+    }
+
+    pub fn nest(indent: usize, doc: Doc) -> Doc {
+        Doc::Nest(indent, Box::new(doc))
+    }
+
+    pub fn align(doc: Doc) -> Doc {
+        Doc::Align(Box::new(doc))
+    }
+
+    pub fn group(doc: Doc) -> Doc {
+        Doc::Group(Box::new(doc))
+    }
+
+    /// `docs` with a `Line` between each pair.
+    pub fn lines(docs: Vec<Doc>) -> Doc {
+        let mut joined = Vec::with_capacity(docs.len() * 2);
+        for (n, doc) in docs.into_iter().enumerate() {
+            if n > 0 {
+                joined.push(Doc::Line);
+            }
+            joined.push(doc);
+        }
+        Doc::Concat(joined)
+    }
+
+    /// Lay the document out to fit in `width` columns where it can.
+    pub fn render(&self, width: usize) -> String {
+        let mut out = String::new();
+        let mut column = 0;
+        // The spaces laid out since the last text, which only get written if
+        // more text follows them on the line
+        let mut spaces = 0;
+        let mut stack = vec![(0, Mode::Break, self)];
+        while let Some((indent, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Nil => {}
+                Doc::Text(s) if s.is_empty() => {}
+                Doc::Text(s) => {
+                    out.extend(std::iter::repeat_n(' ', spaces));
+                    spaces = 0;
+                    out.push_str(s);
+                    column = match s.rfind('\n') {
+                        Some(n) => s[n + 1..].chars().count(),
+                        None => column + s.chars().count(),
+                    };
+                }
+                Doc::Line if mode == Mode::Flat => {
+                    spaces += 1;
+                    column += 1;
+                }
+                Doc::Line | Doc::HardLine => {
+                    // No trailing spaces, but the text's own are kept
+                    out.push('\n');
+                    spaces = indent;
+                    column = indent;
+                }
+                Doc::Concat(docs) => {
+                    stack.extend(docs.iter().rev().map(|d| (indent, mode, d)));
+                }
+                Doc::Nest(n, d) => stack.push((indent + n, mode, d)),
+                Doc::Align(d) => stack.push((column, mode, d)),
+                Doc::Group(d) => {
+                    let flat = mode == Mode::Flat
+                        || fits(width as isize - column as isize, (indent, Mode::Flat, d), &stack);
+                    stack.push((indent, if flat { Mode::Flat } else { Mode::Break }, d));
+                }
+            }
+        }
+        out
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+// Whether everything up to the next line break fits in `width`, with `next`
+// printed first and then the rest of the pending `stack`.
+fn fits(mut width: isize, next: (usize, Mode, &Doc), stack: &[(usize, Mode, &Doc)]) -> bool {
+    let mut rest = stack.iter().rev();
+    let mut pending = vec![next];
+    while width >= 0 {
+        let (indent, mode, doc) = match pending.pop().or_else(|| rest.next().copied()) {
+            Some(item) => item,
+            None => return true,
+        };
+        match doc {
+            Doc::Nil => {}
+            Doc::Text(s) => width -= s.chars().count() as isize,
+            Doc::Line => match mode {
+                Mode::Flat => width -= 1,
+                Mode::Break => return true,
+            },
+            Doc::HardLine => return mode == Mode::Break,
+            Doc::Concat(docs) => pending.extend(docs.iter().rev().map(|d| (indent, mode, d))),
+            Doc::Nest(n, d) => pending.push((indent + n, mode, d)),
+            Doc::Align(d) | Doc::Group(d) => pending.push((indent, mode, d)),
+        }
+    }
+    false
+}
+
+
+
+// Lisp layout
+
+/// How the arguments of a list are laid out when it doesn't fit on one line.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Indent {
+    /// Each argument on its own line, lined up under the first one.
+    Align,
+    /// The first `n` arguments stay on the head's line and the rest are
+    /// indented as a body, like `(lambda (x)` and its body.
+    Body(usize),
+}
+
+/// Layout settings, with `forms` choosing the `Indent` by head symbol.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Options {
+    pub width: usize,
+    /// How far a body is indented past its list's paren.
+    pub body_indent: usize,
+    /// Lists whose head isn't in here use `Indent::Align`.
+    pub forms: HashMap<String, Indent>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        let forms = [
+            ("begin", 0),
+            ("case", 1),
+            ("cond", 0),
+            ("define", 1),
+            ("define-record-type", 1),
+            ("define-syntax", 1),
+            ("define-values", 1),
+            ("do", 2),
+            ("lambda", 1),
+            ("let", 1),
+            ("let*", 1),
+            ("let-syntax", 1),
+            ("let-values", 1),
+            ("letrec", 1),
+            ("letrec*", 1),
+            ("parameterize", 1),
+            ("syntax-rules", 1),
+            ("unless", 1),
+            ("when", 1),
+        ]
+        .iter()
+        .map(|&(head, n)| (head.to_owned(), Indent::Body(n)))
+        .collect();
+        Options {
+            width: 80,
+            body_indent: 2,
+            forms,
+        }
+    }
+}
+
+/// Pretty print `sexp` within `options.width` columns where it can.
+pub fn pretty(sexp: &Sexp, options: &Options) -> String {
+    to_doc(sexp, options).render(options.width)
+}
+
+/// The document `pretty` lays out.
+pub fn to_doc(sexp: &Sexp, options: &Options) -> Doc {
+    match sexp {
+        Sexp::Constant(a) => Doc::text(a.to_string()),
+        Sexp::Quote(q) => {
+            let (prefix, s) = match q {
+                Quote::Quote(s) => ("'", s),
+                Quote::Quasi(s) => ("`", s),
+                Quote::UnQuote(s) => (",", s),
+                Quote::Splice(s) => (",@", s),
+            };
+            match &**s {
+                // Leave the printer to handle `,|@x|`
+                Sexp::Constant(_) => Doc::text(q.to_string()),
+                s => Doc::Concat(vec![Doc::text(prefix), to_doc(s, options)]),
+            }
+        }
+        Sexp::List(items) => sexp_list(items, None, options),
+        // `( . x)` is just `x`, as with `Display`
+        Sexp::DottedList(items, tail) if items.is_empty() => to_doc(tail, options),
+        Sexp::DottedList(items, tail) => sexp_list(items, Some(tail), options),
+        Sexp::Vector(items) => {
+            vector(items.iter().map(|s| Item::plain(to_doc(s, options))).collect())
+        }
+    }
+}
+
+fn sexp_list(items: &[Sexp], tail: Option<&Sexp>, options: &Options) -> Doc {
+    let mut docs: Vec<Item> = items.iter().map(|s| Item::plain(to_doc(s, options))).collect();
+    if let Some(tail) = tail {
+        docs.push(Item::plain(Doc::Concat(vec![Doc::text(". "), to_doc(tail, options)])));
+    }
+    let head = match items.first() {
+        Some(Sexp::Constant(Atom::Symbol(head))) => Some(head.as_str()),
+        _ => None,
+    };
+    list(docs, head, options)
+}
+
+// A list or vector item, and any comments before it.
+struct Item {
+    comments: Comments,
+    doc: Doc,
+}
+
+impl Item {
+    fn plain(doc: Doc) -> Item {
+        Item { comments: Comments::default(), doc }
+    }
+
+    // The item after `sep`, or after nothing if it comes first.
+    fn after(self, sep: Option<Doc>) -> Doc {
+        Doc::Concat(vec![self.comments.between(sep), self.doc])
+    }
+}
+
+// `items` with a `Line` between each pair.
+fn lines(items: Vec<Item>) -> Doc {
+    Doc::Concat(items.into_iter().enumerate()
+        .map(|(n, item)| item.after(if n == 0 { None } else { Some(Doc::Line) }))
+        .collect())
+}
+
+// Vectors have no head, so they always break one item per line.
+fn vector(items: Vec<Item>) -> Doc {
+    Doc::group(Doc::Concat(vec![Doc::text("#("), Doc::align(lines(items)), Doc::text(")")]))
+}
+
+fn list(items: Vec<Item>, head: Option<&str>, options: &Options) -> Doc {
+    let indent = match head {
+        Some(head) => options.forms.get(head).copied().unwrap_or(Indent::Align),
+        // A list starting with data has every item lined up
+        None => return Doc::group(Doc::Concat(vec![
+            Doc::text("("),
+            Doc::align(lines(items)),
+            Doc::text(")"),
+        ])),
+    };
+
+    let mut items = items.into_iter();
+    // Past the paren, in case a comment breaks the line before the head
+    let head = items.next().map_or(Doc::Nil, |item| Doc::nest(1, item.after(None)));
+    let mut args = items.peekable();
+    if args.peek().is_none() {
+        return Doc::Concat(vec![Doc::text("("), head, Doc::text(")")]);
+    }
+    let body = match indent {
+        Indent::Align => {
+            let first = args.next().expect("checked above");
+            // A comment can break the line before the first argument, which
+            // then goes in by a body indent
+            let before = first.comments.between(Some(Doc::text(" ")));
+            let mut aligned = vec![first.doc];
+            aligned.extend(args.map(|arg| arg.after(Some(Doc::Line))));
+            Doc::Concat(vec![
+                Doc::nest(options.body_indent, before),
+                Doc::align(Doc::Concat(aligned)),
+            ])
+        }
+        Indent::Body(n) => {
+            let mut line: Vec<Doc> = args.by_ref()
+                .take(n)
+                .map(|arg| arg.after(Some(Doc::text(" "))))
+                .collect();
+            line.extend(args.map(|arg| arg.after(Some(Doc::Line))));
+            Doc::nest(options.body_indent, Doc::Concat(line))
+        }
+    };
+    // The indent is measured from the paren, so align to it first
+    Doc::align(Doc::group(Doc::Concat(vec![Doc::text("("), head, body, Doc::text(")")])))
+}
+
+
+
+// Source formatting
+
+/// Lay out a whole source text the way `pretty` would, keeping its comments.
+///
+/// Comments stay on the line they were on, after the datum before them or on
+/// lines of their own, and a run of blank lines is kept as one. Atoms keep
+/// their spelling from the source.
+pub fn format(src: &str, options: &Options) -> Result<String, Error> {
+    let root = crate::parse::cst::parse(src)?;
+    let mut docs: Vec<Doc> = root.datums.iter()
+        .enumerate()
+        .map(|(n, datum)| {
+            node_item(datum, options).after(if n == 0 { None } else { Some(Doc::HardLine) })
+        })
+        .collect();
+    let first = root.datums.is_empty();
+    docs.push(closing(&root.trailing, options, first));
+    let out = Doc::Concat(docs).render(options.width);
+    let out = out.trim_end();
+    Ok(if out.is_empty() { String::new() } else { format!("{}\n", out) })
+}
+
+// The comments before a token, split by where they sit.
+#[derive(Default)]
+struct Comments {
+    // Comments on the same line as the token before, each after a space
+    before: Vec<Doc>,
+    // Whether `before` ends in a line comment
+    hard: bool,
+    // Comments that start a line, with what went between them
+    own: Vec<Doc>,
+    // What goes between the last of `own` and the token
+    last: Option<Doc>,
+    // Whether a blank line comes before `own`, or before the token
+    blank: bool,
+}
+
+impl Comments {
+    fn new(trivia: &[Trivia], options: &Options) -> Comments {
+        let mut comments = Comments::default();
+        let mut newlines = 0;
+        for piece in trivia {
+            let (doc, line) = match piece {
+                Trivia::Whitespace(s) => {
+                    newlines += s.matches('\n').count();
+                    continue;
+                }
+                Trivia::LineComment(s) => (Doc::text(s.trim_end()), true),
+                Trivia::BlockComment(s) => (Doc::text(s.as_str()), false),
+                Trivia::DatumComment(datum) => (
+                    Doc::Concat(vec![Doc::text("#;"), node_item(datum, options).after(None)]),
+                    false,
+                ),
+            };
+            if comments.own.is_empty() && newlines == 0 {
+                comments.before.extend(vec![Doc::text(" "), doc]);
+                comments.hard = line;
+            } else {
+                if comments.own.is_empty() {
+                    comments.blank = newlines > 1;
+                } else {
+                    comments.own.push(gap(newlines));
+                }
+                comments.own.push(doc);
+            }
+            newlines = 0;
+        }
+        if comments.own.is_empty() {
+            comments.blank = comments.blank || newlines > 1;
+        } else {
+            comments.last = Some(gap(newlines));
+        }
+        comments
+    }
+
+    // Everything from the token before to this one, where `sep` would go if
+    // there were no comments, or `None` if nothing comes before.
+    fn between(self, sep: Option<Doc>) -> Doc {
+        let mut docs = self.before;
+        if sep.is_none() && !docs.is_empty() {
+            // No space after an open paren or at the start of the file
+            docs.remove(0);
+        }
+        // A blank line is only kept after something
+        let blank = self.blank && (sep.is_some() || !docs.is_empty());
+        let newline = if blank {
+            Doc::Concat(vec![Doc::HardLine, Doc::HardLine])
+        } else {
+            Doc::HardLine
+        };
+        match self.last {
+            None if blank || self.hard => docs.push(newline),
+            None => docs.extend(sep),
+            Some(last) => {
+                if sep.is_some() || !docs.is_empty() {
+                    docs.push(newline);
+                }
+                docs.extend(self.own);
+                docs.push(last);
+            }
+        }
+        Doc::Concat(docs)
+    }
+}
+
+// What goes between two comments `newlines` apart.
+fn gap(newlines: usize) -> Doc {
+    match newlines {
+        0 => Doc::text(" "),
+        1 => Doc::HardLine,
+        _ => Doc::Concat(vec![Doc::HardLine, Doc::HardLine]),
+    }
+}
+
+// The comments before a close paren or the end of the file.
+fn closing(trivia: &[Trivia], options: &Options, first: bool) -> Doc {
+    let mut comments = Comments::new(trivia, options);
+    comments.blank = false;
+    comments.between(if first { None } else { Some(Doc::Nil) })
+}
+
+fn node_item(node: &Node, options: &Options) -> Item {
+    Item {
+        comments: Comments::new(&node.first_token().leading, options),
+        doc: node_doc(node, options),
+    }
+}
+
+// The layout of `node`, leaving out the comments before it.
+fn node_doc(node: &Node, options: &Options) -> Doc {
+    let items = |items: &[Node]| -> Vec<Item> {
+        items.iter().map(|item| node_item(item, options)).collect()
+    };
+    match node {
+        Node::Atom(token) => Doc::text(token.text.as_str()),
+        Node::Quote(prefix, datum) => {
+            Doc::Concat(vec![Doc::text(prefix.text.as_str()), node_item(datum, options).after(None)])
+        }
+        Node::List { items: nodes, tail, close, .. } => {
+            let mut docs = items(nodes);
+            if let Some((dot, tail)) = tail {
+                docs.push(Item {
+                    comments: Comments::new(&dot.leading, options),
+                    doc: Doc::Concat(vec![
+                        Doc::text("."),
+                        node_item(tail, options).after(Some(Doc::text(" "))),
+                    ]),
+                });
+            }
+            let head = match nodes.first() {
+                Some(Node::Atom(token)) => match token.text.parse() {
+                    Ok(Sexp::Constant(Atom::Symbol(head))) => Some(head),
+                    _ => None,
+                },
+                _ => None,
+            };
+            match close_last(&mut docs, &close.leading, options) {
+                Some(close) => Doc::Concat(vec![Doc::text("("), close, Doc::text(")")]),
+                None => list(docs, head.as_deref(), options),
+            }
+        }
+        Node::Vector { items: nodes, close, .. } => {
+            let mut docs = items(nodes);
+            match close_last(&mut docs, &close.leading, options) {
+                Some(close) => Doc::Concat(vec![Doc::text("#("), close, Doc::text(")")]),
+                None => vector(docs),
+            }
+        }
+    }
+}
+
+// Put the comments before a close paren at the end of the last item, so
+// they're indented with it, or give them back if there are no items.
+fn close_last(items: &mut [Item], trivia: &[Trivia], options: &Options) -> Option<Doc> {
+    let close = closing(trivia, options, items.is_empty());
+    match items.last_mut() {
+        Some(last) => {
+            let doc = std::mem::replace(&mut last.doc, Doc::Nil);
+            last.doc = Doc::Concat(vec![doc, close]);
+            None
+        }
+        None => Some(close),
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(src: &str, width: usize) -> String {
+        let options = Options { width, ..Options::default() };
+        pretty(&src.parse().unwrap(), &options)
+    }
+
+    #[test]
+    fn short_forms_stay_flat() {
+        assert_eq!(layout("(define  (f x)\n (g x))", 80), "(define (f x) (g x))");
+        assert_eq!(layout("()", 1), "()");
+        assert_eq!(layout("(f)", 1), "(f)");
+    }
+
+    #[test]
+    fn calls_align_their_arguments() {
+        assert_eq!(
+            layout("(some-function first-argument (nested call) third)", 30),
+            "(some-function first-argument\n               (nested call)\n               third)"
+        );
+    }
+
+    #[test]
+    fn special_forms_indent_their_bodies() {
+        assert_eq!(
+            layout("(define (square x) (display x) (* x x))", 20),
+            "(define (square x)\n  (display x)\n  (* x x))"
+        );
+        assert_eq!(
+            layout("(let ((a 1) (b 2)) (+ a b))", 12),
+            "(let ((a 1)\n      (b 2))\n  (+ a b))"
+        );
+    }
+
+    #[test]
+    fn nested_bodies_indent_from_their_own_paren() {
+        assert_eq!(
+            layout("(define (f) (lambda (x) (when x (display x) (newline))))", 24),
+            "(define (f)\n  (lambda (x)\n    (when x\n      (display x)\n      (newline))))"
+        );
+    }
+
+    #[test]
+    fn data_lists_line_up() {
+        assert_eq!(layout("((a . 1) (b . 2))", 10), "((a . 1)\n (b . 2))");
+        assert_eq!(layout("(x y . z)", 4), "(x y\n   . z)");
+    }
+
+    #[test]
+    fn vectors_go_one_per_line() {
+        assert_eq!(layout("#(1 2 3)", 8), "#(1 2 3)");
+        assert_eq!(layout("#(1 2 3)", 7), "#(1\n  2\n  3)");
+    }
+
+    #[test]
+    fn quotes_wrap_their_datum() {
+        assert_eq!(
+            layout("(list '(alpha beta) `(gamma ,delta))", 20),
+            "(list '(alpha beta)\n      `(gamma ,delta))"
+        );
+    }
+
+    #[test]
+    fn custom_indent_rules() {
+        let mut options = Options { width: 16, ..Options::default() };
+        options.forms.insert("my-macro".to_owned(), Indent::Body(1));
+        options.forms.insert("define".to_owned(), Indent::Align);
+        let sexp = "(my-macro name (one) (two))".parse().unwrap();
+        assert_eq!(pretty(&sexp, &options), "(my-macro name\n  (one)\n  (two))");
+        let sexp = "(define x (one) (two))".parse().unwrap();
+        assert_eq!(pretty(&sexp, &options), "(define x\n        (one)\n        (two))");
+    }
+
+    fn format_at(src: &str, width: usize) -> String {
+        format(src, &Options { width, ..Options::default() }).unwrap()
+    }
+
+    #[test]
+    fn format_keeps_comments() {
+        let src = "; header\n\n\n(define (f x)   ; why\n     #| block |#\n  (g x\n 'y)\n\n\n  ;; own line\n  (h x) ; last\n  )\n(list 1 2)   #;(dropped 1)\n";
+        assert_eq!(
+            format_at(src, 40),
+            "; header\n\n(define (f x) ; why\n  #| block |#\n  (g x 'y)\n\n  ;; own line\n  (h x) ; last\n  )\n(list 1 2) #;(dropped 1)\n"
+        );
+    }
+
+    #[test]
+    fn line_comments_break_their_lists() {
+        assert_eq!(format_at("(foo ; c\n a b)", 80), "(foo ; c\n  a\n  b)\n");
+        assert_eq!(format_at("(#(a ; c\n b))", 80), "(#(a ; c\n   b))\n");
+        assert_eq!(format_at("(a . ; c\n b)", 80), "(a . ; c\n   b)\n");
+    }
+
+    #[test]
+    fn format_matches_pretty_without_comments() {
+        let src = "(define (f . xs)\n #(\"a\" #\\space 1.5) `(,@xs ,(g 'y)))";
+        let sexp: Sexp = src.parse().unwrap();
+        for width in 0..60 {
+            let options = Options { width, ..Options::default() };
+            assert_eq!(format(src, &options), Ok(pretty(&sexp, &options) + "\n"));
+        }
+    }
+
+    #[test]
+    fn format_is_idempotent() {
+        let src = "( ; open\n a #| b |# c\n\n d) ; x\n#;  ; y\n z\n( ; empty\n)\n; end";
+        for width in 0..30 {
+            let once = format_at(src, width);
+            assert_eq!(format_at(&once, width), once, "{}", width);
+            assert_eq!(crate::parse::parse_all(&once), crate::parse::parse_all(src));
+        }
+        assert_eq!(format_at("", 80), "");
+        assert_eq!(format_at(" \n\n", 80), "");
+    }
+
+    #[test]
+    fn breaks_keep_a_char_space() {
+        let src = "(some-function #\\  second-argument third-argument)";
+        let formatted = format_at(src, 20);
+        assert_eq!(formatted, "(some-function #\\ \n               second-argument\n               third-argument)\n");
+        assert_eq!(crate::parse::parse_all(&formatted), crate::parse::parse_all(src));
+    }
+
+    #[test]
+    fn pretty_output_reads_back() {
+        let src = "(define (f . xs) #(\"a\" #\\space 1.5) `(,@xs ,(g 'y)))";
+        let sexp: Sexp = src.parse().unwrap();
+        for width in 0..60 {
+            let options = Options { width, ..Options::default() };
+            assert_eq!(pretty(&sexp, &options).parse::<Sexp>(), Ok(sexp.clone()));
+        }
+    }
+}
@@ -0,0 +1,242 @@
+/// Reading datums one at a time from a `std::io::Read`.
+use std::{io::Read, str};
+
+use crate::{
+    error::{Error, ErrorKind},
+    expr::Sexp,
+    parse::{self, ParseOptions, Scanner},
+};
+
+/// An iterator over the top level datums of a byte stream, such as a pipe or
+/// a socket.
+///
+/// Each datum is yielded as soon as its last byte has arrived, however the
+/// reads split it up. The iterator ends once the stream does, and a stream
+/// that ends inside a datum yields one last error for it, so a clean end
+/// can be told apart from a truncated one. A malformed datum is an error,
+/// but reading carries on after it. Read and UTF-8 errors end the iterator.
+///
+/// Error positions are counted from the start of the stream. Going past one
+/// of the `ParseOptions` limits ends the iterator too, with the default
+/// limits unless the reader is built `with_options`.
+pub struct Reader<R> {
+    inner: R,
+    // What's been read and decoded, but not yet taken
+    text: String,
+    // The bytes read after `text`, which don't make a whole char yet
+    partial: Vec<u8>,
+    scanner: Scanner,
+    eof: bool,
+    // Where the start of `text` is in the stream
+    offset: usize,
+    line: usize,
+    // The text on `line` before `text`, for error snippets
+    line_start: String,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(inner: R) -> Reader<R> {
+        Reader::with_options(inner, ParseOptions::default())
+    }
+
+    /// A reader that keeps to the `options` limits instead of the default ones.
+    pub fn with_options(inner: R, options: ParseOptions) -> Reader<R> {
+        Reader {
+            inner,
+            text: String::new(),
+            partial: Vec::new(),
+            scanner: Scanner::with_options(options),
+            eof: false,
+            offset: 0,
+            line: 1,
+            line_start: String::new(),
+        }
+    }
+
+    /// Give back the underlying reader, dropping anything still buffered.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    // Move the chars that have arrived whole from `partial` to `text`, so each
+    // byte is only checked once. `true` if there's a byte that no char starts
+    // with.
+    fn decode(&mut self) -> bool {
+        match str::from_utf8(&self.partial) {
+            Ok(chars) => {
+                self.text.push_str(chars);
+                self.partial.clear();
+                false
+            }
+            Err(e) => {
+                let valid = e.valid_up_to();
+                self.text.push_str(str::from_utf8(&self.partial[..valid]).expect("valid prefix"));
+                self.partial.drain(..valid);
+                e.error_len().is_some()
+            }
+        }
+    }
+
+    // Parse and drop the first `end` bytes of `text`.
+    fn take(&mut self, end: usize) -> Result<Sexp, Error> {
+        let text = &self.text[..end];
+        let sexp = parse::parse_unchecked(text)
+            .map_err(|e| e.shifted(self.offset, self.line, &self.line_start));
+        match text.rfind('\n') {
+            Some(newline) => {
+                self.line += text.matches('\n').count();
+                self.line_start = text[newline + 1..].to_owned();
+            }
+            None => self.line_start.push_str(text),
+        }
+        self.offset += end;
+        self.text.drain(..end);
+        self.scanner.reset();
+        sexp
+    }
+
+    // The error for a limit the scanner stopped at, if it did.
+    fn exceeded(&mut self) -> Option<Error> {
+        let (limit, max, at) = self.scanner.exceeded()?;
+        Some(self.fail(at, ErrorKind::LimitExceeded { limit, max }))
+    }
+
+    fn fail(&mut self, at: usize, kind: ErrorKind) -> Error {
+        let before = self.text[..at].to_owned();
+        // Nothing more can be read after this
+        self.eof = true;
+        self.text.clear();
+        self.partial.clear();
+        self.scanner.reset();
+        Error::at(&before, before.len(), kind).shifted(self.offset, self.line, &self.line_start)
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = Result<Sexp, Error>;
+
+    fn next(&mut self) -> Option<Result<Sexp, Error>> {
+        let mut chunk = [0; 8192];
+        loop {
+            // A char can be split between reads, so only scan up to it
+            let bad = self.decode();
+            let end = self.scanner.scan(&self.text);
+            if let Some(e) = self.exceeded() {
+                return Some(Err(e));
+            }
+            if let Some(end) = end {
+                return Some(self.take(end));
+            }
+            if bad {
+                return Some(Err(self.fail(self.text.len(), ErrorKind::InvalidUtf8)));
+            }
+            if self.eof {
+                return if !self.partial.is_empty() {
+                    Some(Err(self.fail(self.text.len(), ErrorKind::InvalidUtf8)))
+                } else if self.scanner.is_blank() {
+                    None
+                } else {
+                    // A trailing atom, or a truncated datum for the parser to report
+                    self.scanner.end_input(&self.text);
+                    match self.exceeded() {
+                        Some(e) => Some(Err(e)),
+                        None => Some(self.take(self.text.len())),
+                    }
+                };
+            }
+            match self.inner.read(&mut chunk) {
+                Ok(0) => self.eof = true,
+                Ok(n) => self.partial.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Some(Err(self.fail(self.text.len(), ErrorKind::Io(e.kind())))),
+            }
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    // Hands out `text` a few bytes at a time.
+    struct Trickle<'a> {
+        text: &'a [u8],
+        step: usize,
+    }
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.step.min(self.text.len()).min(buf.len());
+            buf[..n].copy_from_slice(&self.text[..n]);
+            self.text = &self.text[n..];
+            Ok(n)
+        }
+    }
+
+    fn read_all(text: &str, step: usize) -> Vec<Result<Sexp, Error>> {
+        Reader::new(Trickle { text: text.as_bytes(), step }).collect()
+    }
+
+    #[test]
+    fn datums_split_across_reads() {
+        let text = "(define (f x) \"λ \\\"x\\\"\") ; done\n#| c |# 'sym 12345 #\\x";
+        let expected: Vec<_> = parse::parse_all(text).unwrap().into_iter().map(Ok).collect();
+        for step in 1..text.len() {
+            assert_eq!(read_all(text, step), expected, "{}", step);
+        }
+    }
+
+    #[test]
+    fn clean_eof() {
+        assert_eq!(read_all("", 4), vec![]);
+        assert_eq!(read_all("a ; trailing comment", 4).len(), 1);
+        assert_eq!(read_all("a #| c |# #;(dropped)\n", 4).len(), 1);
+    }
+
+    #[test]
+    fn truncated_datum() {
+        let read = read_all("(a) (b (c)", 3);
+        assert_eq!(read.len(), 2);
+        let err = read[1].as_ref().unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::UnclosedParen);
+        assert_eq!(err.offset(), 4);
+        assert_eq!(read_all("\"abc", 2)[0].as_ref().unwrap_err().kind(), &ErrorKind::UnterminatedString);
+        assert_eq!(read_all("'", 2)[0].as_ref().unwrap_err().kind(), &ErrorKind::MissingDatum);
+    }
+
+    #[test]
+    fn errors_keep_reading_with_stream_positions() {
+        let read = read_all("(a)\n  (b ,1) c", 5);
+        assert_eq!(read.len(), 3);
+        let err = read[1].as_ref().unwrap_err();
+        assert_eq!((err.kind(), err.line(), err.column()), (&ErrorKind::UnquoteLiteral, 2, 8));
+        assert!(err.to_string().contains("2 |   (b ,1)\n  |        ^"));
+        assert_eq!(read[2], Ok(Sexp::Constant(crate::expr::Atom::Symbol("c".to_owned()))));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let mut reader = Reader::new(&b"a (b \xff)"[..]);
+        assert!(reader.next().unwrap().is_ok());
+        assert_eq!(reader.next().unwrap().unwrap_err().kind(), &ErrorKind::InvalidUtf8);
+        assert!(reader.next().is_none());
+
+        // Split chars are fine until the stream ends in the middle of one
+        let read = read_all("\"λλ\" (λ) \u{10348}", 1);
+        assert_eq!(read.len(), 3);
+        let read = Reader::new(Trickle { text: b"(a \xce\xbb) \xce", step: 1 }).collect::<Vec<_>>();
+        assert!(read[0].is_ok());
+        let err = read[1].as_ref().unwrap_err();
+        assert_eq!((err.kind(), err.offset()), (&ErrorKind::InvalidUtf8, 7));
+    }
+
+    #[test]
+    fn long_datums_in_small_reads() {
+        let text = format!("\"{}\" (a)", "λ".repeat(100_000));
+        let read = read_all(&text, 3);
+        assert_eq!(read, parse::parse_all(&text).unwrap().into_iter().map(Ok).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,319 @@
+/// Writing Rust values as S-expressions with serde.
+///
+/// - structs are keyword tagged lists, `(:name "rexp" :port 8080)`
+/// - enum variants are their name, or a list headed by it, `(move 1 2)`
+/// - `None` is `nil` and `Some(x)` is just `x`
+/// - sequences and tuples are lists, and maps are lists of `(key value)` pairs
+/// - `()` and unit structs are `()`
+use std::fmt;
+
+use serde::ser::{self, Serialize};
+
+use crate::{
+    expr::{Atom, Num, Sexp},
+    pretty::{self, Options},
+};
+
+/// A value that has no S-expression form, like a `u64` too big for an `i64`
+/// without the `bigint` feature.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Error {
+    message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error { message: msg.to_string() }
+    }
+}
+
+pub fn to_sexp<T: Serialize + ?Sized>(value: &T) -> Result<Sexp, Error> {
+    value.serialize(Serializer)
+}
+
+/// `value` on one line.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    to_sexp(value).map(|sexp| sexp.to_string())
+}
+
+/// `value` laid out by `pretty::pretty` with the default options.
+pub fn to_string_pretty<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    to_sexp(value).map(|sexp| pretty::pretty(&sexp, &Options::default()))
+}
+
+fn symbol(name: &str) -> Sexp {
+    Sexp::Constant(Atom::Symbol(name.to_owned()))
+}
+
+fn keyword(field: &str) -> Sexp {
+    symbol(&format!(":{}", field))
+}
+
+/// Builds the `Sexp` for a value.
+pub struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Sexp;
+    type Error = Error;
+    type SerializeSeq = List;
+    type SerializeTuple = List;
+    type SerializeTupleStruct = List;
+    type SerializeTupleVariant = List;
+    type SerializeMap = Map;
+    type SerializeStruct = List;
+    type SerializeStructVariant = List;
+
+    fn serialize_bool(self, v: bool) -> Result<Sexp, Error> {
+        Ok(Sexp::Constant(Atom::Bool(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Sexp, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Sexp, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Sexp, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Sexp, Error> {
+        Ok(Sexp::Constant(Atom::Num(Num::Int(v))))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Sexp, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Sexp, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Sexp, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Sexp, Error> {
+        use std::convert::TryFrom;
+        match i64::try_from(v) {
+            Ok(v) => Ok(Sexp::Constant(Atom::Num(Num::Int(v)))),
+            #[cfg(feature = "bigint")]
+            Err(_) => Ok(Sexp::Constant(Atom::Num(Num::BigInt(v.into())))),
+            #[cfg(not(feature = "bigint"))]
+            Err(_) => Err(ser::Error::custom(format!("{} is too large for an integer", v))),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Sexp, Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Sexp, Error> {
+        Ok(Sexp::Constant(Atom::Num(Num::Float(v))))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Sexp, Error> {
+        Ok(Sexp::Constant(Atom::Char(v)))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Sexp, Error> {
+        Ok(Sexp::Constant(Atom::String(v.to_owned())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Sexp, Error> {
+        Ok(Sexp::List(v.iter().map(|&b| Sexp::Constant(Atom::Num(Num::Int(b.into())))).collect()))
+    }
+
+    fn serialize_none(self) -> Result<Sexp, Error> {
+        Ok(symbol("nil"))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Sexp, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Sexp, Error> {
+        Ok(Sexp::List(Vec::new()))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Sexp, Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Sexp, Error> {
+        Ok(symbol(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<Sexp, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Sexp, Error> {
+        Ok(Sexp::List(vec![symbol(variant), value.serialize(self)?]))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<List, Error> {
+        Ok(List(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<List, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<List, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<List, Error> {
+        Ok(List(vec![symbol(variant)]))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Map, Error> {
+        Ok(Map { entries: Vec::new(), key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<List, Error> {
+        self.serialize_seq(Some(len * 2))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<List, Error> {
+        Ok(List(vec![symbol(variant)]))
+    }
+}
+
+/// The items of a list so far, for sequences, tuples and structs.
+pub struct List(Vec<Sexp>);
+
+impl ser::SerializeSeq for List {
+    type Ok = Sexp;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.0.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Sexp, Error> {
+        Ok(Sexp::List(self.0))
+    }
+}
+
+impl ser::SerializeTuple for List {
+    type Ok = Sexp;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Sexp, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for List {
+    type Ok = Sexp;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Sexp, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for List {
+    type Ok = Sexp;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Sexp, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeStruct for List {
+    type Ok = Sexp;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.0.push(keyword(key));
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Sexp, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for List {
+    type Ok = Sexp;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Sexp, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// The `(key value)` pairs of a map so far.
+pub struct Map {
+    entries: Vec<Sexp>,
+    key: Option<Sexp>,
+}
+
+impl ser::SerializeMap for Map {
+    type Ok = Sexp;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(key.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().expect("serialize_key comes first");
+        self.entries.push(Sexp::List(vec![key, value.serialize(Serializer)?]));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Sexp, Error> {
+        Ok(Sexp::List(self.entries))
+    }
+}
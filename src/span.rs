@@ -0,0 +1,188 @@
+/// Source locations for the spanned variant of the Symbolic Expression types.
+use crate::expr::{Atom, Quote, Sexp};
+
+/// A half open byte range `start..end` into the parsed source.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// A node together with the bytes of source it was read from.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Spanned<T> {
+        Spanned { node, span }
+    }
+}
+
+/// `Sexp` where every child carries its own `Span`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum SpannedSexp {
+    Quote(SpannedQuote),
+    Constant(Atom),
+    List(Vec<Spanned<SpannedSexp>>),
+    DottedList(Vec<Spanned<SpannedSexp>>, Box<Spanned<SpannedSexp>>),
+    Vector(Vec<Spanned<SpannedSexp>>),
+}
+
+/// `Quote` where the quoted datum carries its own `Span`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum SpannedQuote {
+    Quote(Box<Spanned<SpannedSexp>>),
+    Quasi(Box<Spanned<SpannedSexp>>),
+    UnQuote(Box<Spanned<SpannedSexp>>),
+    Splice(Box<Spanned<SpannedSexp>>),
+}
+
+// Dropping the spans gives back the plain tree.
+
+impl From<Spanned<SpannedSexp>> for Sexp {
+    fn from(spanned: Spanned<SpannedSexp>) -> Sexp {
+        Sexp::from(spanned.node)
+    }
+}
+
+impl From<SpannedSexp> for Sexp {
+    fn from(sexp: SpannedSexp) -> Sexp {
+        fn strip(items: Vec<Spanned<SpannedSexp>>) -> Vec<Sexp> {
+            items.into_iter().map(Sexp::from).collect()
+        }
+        match sexp {
+            SpannedSexp::Quote(q) => Sexp::Quote(Quote::from(q)),
+            SpannedSexp::Constant(a) => Sexp::Constant(a),
+            SpannedSexp::List(items) => Sexp::List(strip(items)),
+            SpannedSexp::DottedList(items, tail) => {
+                Sexp::DottedList(strip(items), Box::new(Sexp::from(*tail)))
+            }
+            SpannedSexp::Vector(items) => Sexp::Vector(strip(items)),
+        }
+    }
+}
+
+impl From<SpannedQuote> for Quote {
+    fn from(quote: SpannedQuote) -> Quote {
+        let strip = |s: Box<Spanned<SpannedSexp>>| Box::new(Sexp::from(*s));
+        match quote {
+            SpannedQuote::Quote(s) => Quote::Quote(strip(s)),
+            SpannedQuote::Quasi(s) => Quote::Quasi(strip(s)),
+            SpannedQuote::UnQuote(s) => Quote::UnQuote(strip(s)),
+            SpannedQuote::Splice(s) => Quote::Splice(strip(s)),
+        }
+    }
+}
+
+
+
+// Line and column lookup
+
+/// A 1 based line and column, where the column counts `char`s.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Maps byte offsets in a source text to lines and columns.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    src: &'a str,
+    // Byte offset of the first character of each line
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(src: &'a str) -> LineIndex<'a> {
+        let starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { src, starts }
+    }
+
+    /// Offsets past the end of the source are clamped to the end.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let offset = offset.min(self.src.len());
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let start = self.starts[line];
+        let column = self.src[start..]
+            .char_indices()
+            .take_while(|(i, _)| start + i < offset)
+            .count();
+        LineCol { line: line + 1, column: column + 1 }
+    }
+
+    /// The text of a 1 based line, without its line ending.
+    pub fn line_text(&self, line: usize) -> Option<&'a str> {
+        let start = *self.starts.get(line.checked_sub(1)?)?;
+        let end = self.starts.get(line).map_or(self.src.len(), |next| next - 1);
+        Some(self.src[start..end].trim_end_matches('\r'))
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.starts.len()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_line_columns() {
+        let index = LineIndex::new("(abc)");
+        assert_eq!(index.line_col(0), LineCol { line: 1, column: 1 });
+        assert_eq!(index.line_col(4), LineCol { line: 1, column: 5 });
+    }
+
+    #[test]
+    fn later_lines() {
+        let index = LineIndex::new("(a\n  b\n)\n");
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_col(3), LineCol { line: 2, column: 1 });
+        assert_eq!(index.line_col(5), LineCol { line: 2, column: 3 });
+        assert_eq!(index.line_col(7), LineCol { line: 3, column: 1 });
+        // The end of input sits on the empty last line
+        assert_eq!(index.line_col(9), LineCol { line: 4, column: 1 });
+        assert_eq!(index.line_col(100), LineCol { line: 4, column: 1 });
+    }
+
+    #[test]
+    fn columns_count_chars() {
+        let index = LineIndex::new("\"λx\" y");
+        assert_eq!(index.line_col(5), LineCol { line: 1, column: 5 });
+    }
+
+    #[test]
+    fn line_texts() {
+        let index = LineIndex::new("one\r\ntwo\nthree");
+        assert_eq!(index.line_text(0), None);
+        assert_eq!(index.line_text(1), Some("one"));
+        assert_eq!(index.line_text(2), Some("two"));
+        assert_eq!(index.line_text(3), Some("three"));
+        assert_eq!(index.line_text(4), None);
+    }
+}
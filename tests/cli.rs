@@ -0,0 +1,125 @@
+#![cfg(feature = "cli")]
+
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+// A fresh directory for one test to work in.
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rexp-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn rexp(dir: &PathBuf, args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rexp"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn check_reports_every_error() {
+    let dir = scratch("check");
+    fs::write(dir.join("good.scm"), "(a b)\n").unwrap();
+    fs::write(dir.join("bad.scm"), "(a\n  \"b)\n").unwrap();
+    fs::write(dir.join("worse.scm"), "(a . b c)").unwrap();
+
+    let output = rexp(&dir, &["check", "good.scm"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stderr(&output), "");
+
+    let output = rexp(&dir, &["check", "good.scm", "bad.scm", "worse.scm"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "bad.scm:2:3: unterminated string, expected `\"`\n\
+         worse.scm:1:8: misplaced `.`, it may only come before the last datum of a list\n"
+    );
+
+    let output = rexp(&dir, &["check"], "(oops");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("<stdin>:1:1: unclosed paren"));
+}
+
+#[test]
+fn check_goes_on_past_unreadable_files() {
+    let dir = scratch("unreadable");
+    fs::write(dir.join("bad.scm"), "(a . b c)").unwrap();
+    let output = rexp(&dir, &["check", "missing.scm", "bad.scm"], "");
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = stderr(&output);
+    assert!(stderr.starts_with("rexp: missing.scm: "), "{}", stderr);
+    assert!(stderr.ends_with("\nbad.scm:1:8: misplaced `.`, it may only come before the last datum of a list\n"), "{}", stderr);
+}
+
+#[test]
+fn print_pretty_prints() {
+    let dir = scratch("print");
+    let output = rexp(&dir, &["print", "--width", "20"], "(define (square x) (display x) (* x x)) a");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "(define (square x)\n  (display x)\n  (* x x))\na\n");
+}
+
+#[test]
+fn json_both_ways() {
+    let dir = scratch("json");
+    let src = "(define x \"s\") #(1 2.5) '(a . b)";
+    let output = rexp(&dir, &["to-json"], src);
+    assert_eq!(output.status.code(), Some(0));
+    let json = stdout(&output);
+    assert_eq!(
+        json,
+        "[[\"define\",\"x\",{\"string\":\"s\"}],{\"vector\":[1,2.5]},{\"quote\":{\"dotted\":[\"a\",\"b\"]}}]\n"
+    );
+
+    let output = rexp(&dir, &["from-json"], &json);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "(define x \"s\")\n#(1 2.5)\n'(a . b)\n");
+
+    let output = rexp(&dir, &["from-json"], "[null]");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "<stdin>: null has no sexp form\n");
+    assert_eq!(rexp(&dir, &["from-json"], "[1,").status.code(), Some(1));
+}
+
+#[test]
+fn query_selects_by_path() {
+    let dir = scratch("query");
+    fs::write(dir.join("a.scm"), "(define (f x) x)\n(define y 2)\n(other z)\n").unwrap();
+    let output = rexp(&dir, &["query", "define/1", "a.scm"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "(f x)\ny\n");
+
+    let output = rexp(&dir, &["query", "lambda", "a.scm"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn usage_errors_exit_with_2() {
+    let dir = scratch("usage");
+    for args in &[&[][..], &["frobnicate"], &["print", "--width"], &["check", "missing.scm"], &["query"]] {
+        let output = rexp(&dir, args, "");
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(stderr(&output).starts_with("rexp: "), "{:?}", args);
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::{BTreeMap, HashMap};
+
+use rexp::expr::{FromSexp, FromSexpError, Sexp, ToSexp};
+// With the `derive` feature `rexp::expr` has the derives too
+#[cfg(not(feature = "derive"))]
+use rexp_derive::{FromSexp, ToSexp};
+
+fn sexp(src: &str) -> Sexp {
+    src.parse().unwrap()
+}
+
+fn read<T: FromSexp>(src: &str) -> Result<T, FromSexpError> {
+    T::from_sexp(&sexp(src))
+}
+
+fn message<T: FromSexp + std::fmt::Debug>(src: &str) -> String {
+    read::<T>(src).unwrap_err().message().to_owned()
+}
+
+#[derive(Debug, PartialEq, ToSexp, FromSexp)]
+struct Server {
+    name: String,
+    port: u16,
+    #[sexp(rename = "allow")]
+    allowed: Vec<String>,
+    #[sexp(default)]
+    backup: Option<Box<Server>>,
+}
+
+#[derive(Debug, PartialEq, ToSexp, FromSexp)]
+struct Define {
+    #[sexp(positional)]
+    name: String,
+    #[sexp(positional)]
+    value: i64,
+    #[sexp(default = "Define::undocumented")]
+    doc: String,
+}
+
+impl Define {
+    fn undocumented() -> String {
+        "none".to_owned()
+    }
+}
+
+#[derive(Debug, PartialEq, ToSexp, FromSexp)]
+#[sexp(positional)]
+struct Point {
+    x: f64,
+    y: f64,
+    #[sexp(default)]
+    z: f64,
+}
+
+#[derive(Debug, PartialEq, ToSexp, FromSexp)]
+enum Op {
+    #[sexp(rename = "halt")]
+    Halt,
+    Push(i64),
+    Move(u8, u8),
+    Jump { to: Label, #[sexp(default)] when: Option<bool> },
+    #[sexp(positional)]
+    Call { target: Label, args: Vec<i64> },
+}
+
+#[derive(Debug, PartialEq, ToSexp, FromSexp)]
+struct Label(String);
+
+#[derive(Debug, PartialEq, ToSexp, FromSexp)]
+struct Pair<T>(T, T);
+
+#[derive(Debug, PartialEq, ToSexp, FromSexp)]
+struct Nothing;
+
+fn round_trip<T: ToSexp + FromSexp + PartialEq + std::fmt::Debug>(value: T, src: &str) {
+    assert_eq!(value.to_sexp(), sexp(src));
+    assert_eq!(read::<T>(src), Ok(value));
+}
+
+#[test]
+fn primitives() {
+    round_trip(true, "#t");
+    round_trip('x', "#\\x");
+    round_trip(-7i8, "-7");
+    round_trip(u32::MAX, "4294967295");
+    round_trip(2.5f64, "2.5");
+    round_trip("hi".to_owned(), "\"hi\"");
+    round_trip(Some(1u8), "1");
+    round_trip(None::<u8>, "nil");
+    round_trip(vec![1usize, 2], "(1 2)");
+    round_trip((1i32, 'a', "b".to_owned()), "(1 #\\a \"b\")");
+    round_trip((), "()");
+    round_trip(sexp("(a . b)"), "(a . b)");
+
+    let mut map = BTreeMap::new();
+    map.insert(1u8, "one".to_owned());
+    map.insert(2u8, "two".to_owned());
+    round_trip(map, "((1 \"one\") (2 \"two\"))");
+    let map: HashMap<String, i64> = read("((a 1) (b 2))").unwrap();
+    assert_eq!(map["b"], 2);
+}
+
+#[test]
+fn big_unsigned() {
+    round_trip(i64::MAX as u64, "9223372036854775807");
+    #[cfg(feature = "bigint")]
+    round_trip(u64::MAX, "18446744073709551615");
+    // Without `bigint` the nearest float is as close as it gets
+    #[cfg(not(feature = "bigint"))]
+    assert_eq!(u64::MAX.to_sexp(), sexp("1.8446744073709552e19"));
+}
+
+#[test]
+fn lenient_primitives() {
+    assert_eq!(read::<String>("sym"), Ok("sym".to_owned()));
+    assert_eq!(read::<f64>("3"), Ok(3.0));
+    assert_eq!(read::<f32>("1/4"), Ok(0.25));
+    assert_eq!(read::<Vec<u8>>("#(1 2)"), Ok(vec![1, 2]));
+}
+
+#[test]
+fn primitive_errors() {
+    assert_eq!(message::<u8>("256"), "256 doesn't fit in u8");
+    assert_eq!(message::<u64>("-1"), "-1 doesn't fit in u64");
+    assert_eq!(message::<i64>("1.5"), "expected an integer, found 1.5");
+    assert_eq!(message::<bool>("t"), "expected a boolean, found t");
+    assert_eq!(message::<(u8, u8)>("(1 2 3)"), "expected a list of length 2, found (1 2 3)");
+    assert_eq!(message::<Vec<u8>>("(1 x)"), "expected an integer, found x");
+}
+
+#[test]
+fn structs_are_keyword_lists() {
+    let server = Server {
+        name: "a".to_owned(),
+        port: 80,
+        allowed: vec!["me".to_owned()],
+        backup: Some(Box::new(Server { name: "b".to_owned(), port: 81, allowed: Vec::new(), backup: None })),
+    };
+    round_trip(server, "(:name \"a\" :port 80 :allow (\"me\") :backup (:name \"b\" :port 81 :allow () :backup nil))");
+    // Keywords come in any order, and defaulted ones can be left out
+    assert_eq!(
+        read::<Server>("(:port 1 :allow () :name x)").map(|s| (s.name, s.backup)),
+        Ok(("x".to_owned(), None))
+    );
+}
+
+#[test]
+fn positional_fields() {
+    round_trip(
+        Define { name: "x".to_owned(), value: 1, doc: "the x".to_owned() },
+        "(\"x\" 1 :doc \"the x\")",
+    );
+    assert_eq!(read::<Define>("(x 1)").map(|d| d.doc), Ok("none".to_owned()));
+    round_trip(Point { x: 1.0, y: 2.0, z: 3.0 }, "(1.0 2.0 3.0)");
+    assert_eq!(read::<Point>("(1 2)"), Ok(Point { x: 1.0, y: 2.0, z: 0.0 }));
+}
+
+#[test]
+fn enums_are_tagged_forms() {
+    let ops = vec![
+        Op::Halt,
+        Op::Push(-1),
+        Op::Move(1, 2),
+        Op::Jump { to: Label("top".to_owned()), when: Some(false) },
+        Op::Call { target: Label("f".to_owned()), args: vec![1, 2] },
+    ];
+    round_trip(
+        ops,
+        "(halt (Push -1) (Move 1 2) (Jump :to \"top\" :when #f) (Call \"f\" (1 2)))",
+    );
+    assert_eq!(read::<Op>("(halt)"), Ok(Op::Halt));
+    assert_eq!(read::<Op>("(Jump :to top)"), Ok(Op::Jump { to: Label("top".to_owned()), when: None }));
+}
+
+#[test]
+fn other_structs() {
+    round_trip(Label("l".to_owned()), "\"l\"");
+    round_trip(Pair('a', 'b'), "(#\\a #\\b)");
+    round_trip(Nothing, "()");
+}
+
+#[test]
+fn derived_errors() {
+    assert_eq!(message::<Server>("(:name a :port 1)"), "missing field `allow`");
+    assert_eq!(message::<Server>("(:name a :port x :allow ())"), "in `port`, expected an integer, found x");
+    assert_eq!(
+        message::<Server>("(:name a :port 1 :allow () :backup (:name b :port -1 :allow ()))"),
+        "in `backup`, in `port`, -1 doesn't fit in u16"
+    );
+    assert_eq!(
+        message::<Server>("(:name a :host b)"),
+        "unknown field `:host`, expected one of `:name`, `:port`, `:allow`, `:backup`"
+    );
+    assert_eq!(message::<Server>("(:name a :name b)"), "`:name` is given twice");
+    assert_eq!(message::<Server>("(:name)"), "`:name` has no value");
+    assert_eq!(message::<Server>("(name a)"), "expected a keyword like `:name`, found name");
+    assert_eq!(message::<Server>("name"), "expected a list, found name");
+    assert_eq!(message::<Define>("(x)"), "missing field `value`");
+    assert_eq!(message::<Pair<u8>>("(1 2 3)"), "too many items, expected at most 2");
+    assert_eq!(message::<Op>("(halt 1)"), "too many items, expected at most 0");
+    assert_eq!(
+        message::<Op>("(Pop)"),
+        "unknown variant `Pop`, expected one of `halt`, `Push`, `Move`, `Jump`, `Call`"
+    );
+    assert_eq!(message::<Op>("(1 2)"), "expected a list headed by a symbol, found (1 2)");
+}
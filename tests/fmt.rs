@@ -0,0 +1,128 @@
+#![cfg(feature = "cli")]
+
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+const MESSY: &str = "(define (f x)   ; why\n     (g x\n 'y))\n";
+const TIDY: &str = "(define (f x) ; why\n  (g x 'y))\n";
+
+// A fresh directory for one test to work in.
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rexp-fmt-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn rexp_fmt(dir: &PathBuf, args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rexp-fmt"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn formats_files_in_place() {
+    let dir = scratch("write");
+    fs::write(dir.join("a.scm"), MESSY).unwrap();
+    fs::write(dir.join("b.scm"), TIDY).unwrap();
+    let output = rexp_fmt(&dir, &["a.scm", "b.scm"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(fs::read_to_string(dir.join("a.scm")).unwrap(), TIDY);
+    assert_eq!(fs::read_to_string(dir.join("b.scm")).unwrap(), TIDY);
+}
+
+#[test]
+fn formats_stdin() {
+    let dir = scratch("stdin");
+    let output = rexp_fmt(&dir, &[], MESSY);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), TIDY);
+}
+
+#[test]
+fn check_lists_unformatted_files() {
+    let dir = scratch("check");
+    fs::write(dir.join("a.scm"), MESSY).unwrap();
+    fs::write(dir.join("b.scm"), TIDY).unwrap();
+    let output = rexp_fmt(&dir, &["--check", "a.scm", "b.scm"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "a.scm\n");
+    assert_eq!(fs::read_to_string(dir.join("a.scm")).unwrap(), MESSY);
+    assert_eq!(rexp_fmt(&dir, &["--check", "b.scm"], "").status.code(), Some(0));
+}
+
+#[test]
+fn diff_shows_the_changes() {
+    let dir = scratch("diff");
+    fs::write(dir.join("a.scm"), MESSY).unwrap();
+    let output = rexp_fmt(&dir, &["--diff", "a.scm"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "--- a.scm\n+++ a.scm (formatted)\n@@ -1,3 +1,2 @@\n\
+         -(define (f x)   ; why\n-     (g x\n- 'y))\n\
+         +(define (f x) ; why\n+  (g x 'y))\n"
+    );
+    assert_eq!(fs::read_to_string(dir.join("a.scm")).unwrap(), MESSY);
+}
+
+#[test]
+fn diff_shows_a_missing_newline() {
+    let dir = scratch("newline");
+    fs::write(dir.join("a.scm"), TIDY.trim_end()).unwrap();
+    let output = rexp_fmt(&dir, &["--diff", "a.scm"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "--- a.scm\n+++ a.scm (formatted)\n@@ -1,2 +1,2 @@\n (define (f x) ; why\n\
+         -  (g x 'y))\n\\ No newline at end of file\n+  (g x 'y))\n"
+    );
+}
+
+#[test]
+fn reads_the_nearest_config() {
+    let dir = scratch("config");
+    let nested = dir.join("nested");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(dir.join("rexp-fmt.toml"), "width = 12\nindent = 4\n").unwrap();
+    let output = rexp_fmt(&nested, &[], "(define (f x) (g x))");
+    assert_eq!(stdout(&output), "(define (f x)\n    (g x))\n");
+
+    fs::write(nested.join("other.toml"), "[forms]\ndefine = \"align\"\n").unwrap();
+    let output = rexp_fmt(&nested, &["--config", "other.toml"], "(define (f x) (g x))");
+    assert_eq!(stdout(&output), "(define (f x) (g x))\n");
+}
+
+#[test]
+fn errors_exit_with_2() {
+    let dir = scratch("errors");
+    fs::write(dir.join("bad.scm"), "(a\n").unwrap();
+    fs::write(dir.join("good.scm"), MESSY).unwrap();
+    let output = rexp_fmt(&dir, &["bad.scm", "good.scm", "missing.scm"], "");
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("bad.scm: "), "{}", stderr);
+    assert!(stderr.contains("missing.scm: "), "{}", stderr);
+    // The other files are still formatted
+    assert_eq!(fs::read_to_string(dir.join("good.scm")).unwrap(), TIDY);
+
+    fs::write(dir.join("rexp-fmt.toml"), "colour = \"blue\"\n").unwrap();
+    let output = rexp_fmt(&dir, &["good.scm"], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("unknown setting `colour`"));
+}
@@ -0,0 +1,163 @@
+// Conformance table for numeric literals
+
+use rexp::{
+    error::ErrorKind,
+    expr::{Atom, Num, Ratio as R, Sexp},
+    parse,
+};
+
+enum Reads {
+    Int(i64),
+    Float(f64),
+    Ratio(i64, i64),
+    Nan,
+    Symbol,
+    Error(ErrorKind),
+}
+
+use Reads::*;
+
+const TABLE: &[(&str, Reads)] = &[
+    // Integers
+    ("0", Int(0)),
+    ("42", Int(42)),
+    ("+5", Int(5)),
+    ("-5", Int(-5)),
+    ("007", Int(7)),
+    ("1_000", Int(1000)),
+    ("-1_000_000", Int(-1_000_000)),
+    ("9223372036854775807", Int(i64::MAX)),
+    ("-9223372036854775808", Int(i64::MIN)),
+    // Radix prefixes
+    ("#xFF", Int(255)),
+    ("#xff", Int(255)),
+    ("#X-1a", Int(-26)),
+    ("#b1010", Int(10)),
+    ("#b-101", Int(-5)),
+    ("#o777", Int(511)),
+    ("#d99", Int(99)),
+    ("#x7fff_ffff", Int(0x7fff_ffff)),
+    // Decimals
+    ("1.5", Float(1.5)),
+    ("-254.345", Float(-254.345)),
+    (".5", Float(0.5)),
+    ("-.5", Float(-0.5)),
+    ("5.", Float(5.0)),
+    ("+5.", Float(5.0)),
+    ("1e10", Float(1e10)),
+    ("1E10", Float(1e10)),
+    ("1e-3", Float(1e-3)),
+    ("2.5e+2", Float(250.0)),
+    (".5e1", Float(5.0)),
+    ("1_000.000_1", Float(1000.0001)),
+    ("#d1.5", Float(1.5)),
+    // Exactness
+    ("#e1.0", Int(1)),
+    ("#e1e3", Int(1000)),
+    ("#e1.50e1", Int(15)),
+    ("#e-0.0", Int(0)),
+    ("#i5", Float(5.0)),
+    ("#i-5", Float(-5.0)),
+    ("#x#iFF", Float(255.0)),
+    ("#i#xFF", Float(255.0)),
+    ("#e#x10", Int(16)),
+    ("#i1.5", Float(1.5)),
+    ("#e1.5", Ratio(3, 2)),
+    ("#e-0.125", Ratio(-1, 8)),
+    ("#e1.25e-1", Ratio(1, 8)),
+    // Ratios
+    ("1/3", Ratio(1, 3)),
+    ("-2/4", Ratio(-1, 2)),
+    ("+6/4", Ratio(3, 2)),
+    ("4/2", Int(2)),
+    ("0/5", Int(0)),
+    ("#x1/A", Ratio(1, 10)),
+    ("#b-1/11", Ratio(-1, 3)),
+    ("1_0/3", Ratio(10, 3)),
+    ("#i1/4", Float(0.25)),
+    ("#i-3/2", Float(-1.5)),
+    ("1/0", Error(ErrorKind::DivisionByZero)),
+    ("#x0/00", Error(ErrorKind::DivisionByZero)),
+    // Infinities and NaN
+    ("+inf.0", Float(f64::INFINITY)),
+    ("-inf.0", Float(f64::NEG_INFINITY)),
+    ("+INF.0", Float(f64::INFINITY)),
+    ("+nan.0", Nan),
+    ("-nan.0", Nan),
+    ("#i+inf.0", Float(f64::INFINITY)),
+    // Not numbers
+    ("inf.0", Symbol),
+    ("nan.0", Symbol),
+    ("+", Symbol),
+    ("-", Symbol),
+    ("...", Symbol),
+    ("-foo", Symbol),
+    ("_1", Symbol),
+    ("#e+inf.0", Error(ErrorKind::MalformedNumber)),
+    ("/2", Symbol),
+    // Number shaped, but not numbers
+    ("1+", Error(ErrorKind::MalformedNumber)),
+    ("1_", Error(ErrorKind::MalformedNumber)),
+    ("1__0", Error(ErrorKind::MalformedNumber)),
+    ("123abc", Error(ErrorKind::MalformedNumber)),
+    ("12.5.3", Error(ErrorKind::MalformedNumber)),
+    ("-.5x", Error(ErrorKind::MalformedNumber)),
+    ("#x1.5", Error(ErrorKind::MalformedNumber)),
+    ("#x#x1", Error(ErrorKind::MalformedNumber)),
+    ("#e#i1", Error(ErrorKind::MalformedNumber)),
+    ("#xZ", Error(ErrorKind::InvalidSymbol)),
+    ("1/", Error(ErrorKind::MalformedNumber)),
+    ("1/-2", Error(ErrorKind::MalformedNumber)),
+    ("1/2/3", Error(ErrorKind::MalformedNumber)),
+    ("1.5/2", Error(ErrorKind::MalformedNumber)),
+    // Too big
+    ("#e1e5000", Error(ErrorKind::IntegerOverflow)),
+    ("#e1e-4294967296", Error(ErrorKind::IntegerOverflow)),
+    ("#e1e-9223372036854775808", Error(ErrorKind::IntegerOverflow)),
+    ("#e1.5e-9223372036854775808", Error(ErrorKind::IntegerOverflow)),
+];
+
+#[test]
+fn numeric_literal_conformance() {
+    for (src, expected) in TABLE {
+        let read = parse::parse(src);
+        let ok = match (expected, &read) {
+            (Int(i), Ok(Sexp::Constant(Atom::Num(Num::Int(n))))) => i == n,
+            (Float(x), Ok(Sexp::Constant(Atom::Num(Num::Float(y))))) => x == y,
+            (Ratio(n, d), Ok(Sexp::Constant(Atom::Num(Num::Ratio(r))))) => (*n, *d) == (r.numer(), r.denom()),
+            (Nan, Ok(Sexp::Constant(Atom::Num(Num::Float(y))))) => y.is_nan(),
+            (Symbol, Ok(Sexp::Constant(Atom::Symbol(s)))) => s == src,
+            (Error(kind), Err(e)) => e.kind() == kind,
+            _ => false,
+        };
+        assert!(ok, "{} read as {:?}", src, read);
+    }
+}
+
+#[cfg(not(feature = "bigint"))]
+#[test]
+fn overflow_in_any_radix() {
+    for src in &["#x10000000000000000", "#b-1_0000000000000000000000000000000000000000000000000000000000000000", "#e1e19"] {
+        assert_eq!(parse::parse(src).unwrap_err().kind(), &ErrorKind::IntegerOverflow, "{}", src);
+    }
+}
+
+#[test]
+fn ratios_are_normalized() {
+    assert_eq!(R::new(6, -4).map(|r| (r.numer(), r.denom())), Some((-3, 2)));
+    assert_eq!(R::new(0, -7).map(|r| (r.numer(), r.denom())), Some((0, 1)));
+    assert_eq!(R::new(i64::MIN, i64::MIN).map(|r| (r.numer(), r.denom())), Some((1, 1)));
+    assert_eq!(R::new(1, 0), None);
+    // `-i64::MIN` doesn't fit
+    assert_eq!(R::new(i64::MIN, -1), None);
+    assert_eq!(R::new(-3, 6).map(|r| r.to_string()), Some("-1/2".to_owned()));
+}
+
+#[test]
+fn floats_print_so_they_read_back() {
+    for x in &[1.0, -0.0, 0.1, 1e300, 1.5e-300, 123456789.125, f64::INFINITY, f64::NEG_INFINITY] {
+        let printed = Num::Float(*x).to_string();
+        assert_eq!(parse::parse(&printed), Ok(Sexp::Constant(Atom::Num(Num::Float(*x)))), "{}", printed);
+    }
+    assert_eq!(Num::Float(f64::NAN).to_string(), "+nan.0");
+}
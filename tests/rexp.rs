@@ -6,8 +6,8 @@ use rexp::{
         Quote::{
             Quasi,
             Quote,
-            //Splice,
-            //UnQuote,
+            Splice,
+            UnQuote,
         },
         Sexp,
     },
@@ -97,7 +97,7 @@ fn quoted_int() {
 fn cannot_splice_unquote_int() {
     assert!(parse::sexp(",345").is_err());
 
-    assert!(parse::sexp("@345").is_err());
+    assert!(parse::sexp(",@345").is_err());
 }
 
 #[test]
@@ -136,7 +136,7 @@ fn quoted_float() {
 fn cannot_splice_or_quote_float() {
     assert!(parse::sexp(",756.314").is_err());
 
-    assert!(parse::sexp("@756.314").is_err());
+    assert!(parse::sexp(",@756.314").is_err());
 }
 
 #[test]
@@ -299,3 +299,531 @@ fn quoted_list() {
         Ok(("", Sexp::Quote(Quote(Box::new(Sexp::List(vec![]))))))
     );
 }
+
+
+
+// Programs
+
+#[test]
+fn empty_program() {
+    assert_eq!(parse::parse_all(""), Ok(vec![]));
+    assert_eq!(parse::parse_all("  \n\t "), Ok(vec![]));
+}
+
+#[test]
+fn program_of_many_datums() {
+    assert_eq!(
+        parse::parse_all("\n  (define x 1)\n'x  \"done\"\n"),
+        Ok(vec![
+            Sexp::List(vec![
+                Sexp::Constant(Atom::Symbol("define".to_owned())),
+                Sexp::Constant(Atom::Symbol("x".to_owned())),
+                Sexp::Constant(Atom::Num(Num::Int(1))),
+            ]),
+            Sexp::Quote(Quote(Box::new(Sexp::Constant(Atom::Symbol("x".to_owned()))))),
+            Sexp::Constant(Atom::String("done".to_owned())),
+        ])
+    );
+}
+
+#[test]
+fn program_consumes_all_input() {
+    assert_eq!(
+        parse::program("a b "),
+        Ok(("", vec![
+            Sexp::Constant(Atom::Symbol("a".to_owned())),
+            Sexp::Constant(Atom::Symbol("b".to_owned())),
+        ]))
+    );
+}
+
+#[test]
+fn program_fails_on_trailing_garbage() {
+    assert!(parse::parse_all("(a b))").is_err());
+    assert!(parse::program("(a) )").is_err());
+}
+
+
+
+// Comments
+
+#[test]
+fn line_comments_are_whitespace() {
+    assert_eq!(
+        parse::parse_all("; leading note\n(a ; trailing note\n b) ; last\n"),
+        Ok(vec![Sexp::List(vec![
+            Sexp::Constant(Atom::Symbol("a".to_owned())),
+            Sexp::Constant(Atom::Symbol("b".to_owned())),
+        ])])
+    );
+    assert_eq!(
+        parse::parse_all("(a;note\n)"),
+        Ok(vec![Sexp::List(vec![
+            Sexp::Constant(Atom::Symbol("a".to_owned())),
+        ])])
+    );
+}
+
+#[test]
+fn nested_block_comments_are_whitespace() {
+    assert_eq!(
+        parse::parse_all("#| outer #| inner |# still outer |# #(1 #| two |# 3)"),
+        Ok(vec![Sexp::Vector(vec![
+            Sexp::Constant(Atom::Num(Num::Int(1))),
+            Sexp::Constant(Atom::Num(Num::Int(3))),
+        ])])
+    );
+}
+
+#[test]
+fn unterminated_block_comment_fails() {
+    assert!(parse::parse_all("#| outer #| inner |# (a b)").is_err());
+    assert!(parse::parse_all("(a #| b)").is_err());
+}
+
+#[test]
+fn datum_comments_skip_one_datum() {
+    assert_eq!(
+        parse::parse_all("#;(ignored (entirely)) (a #; b c #;\n d)"),
+        Ok(vec![Sexp::List(vec![
+            Sexp::Constant(Atom::Symbol("a".to_owned())),
+            Sexp::Constant(Atom::Symbol("c".to_owned())),
+        ])])
+    );
+    // Datum comments stack
+    assert_eq!(
+        parse::parse_all("#; #; a b c"),
+        Ok(vec![Sexp::Constant(Atom::Symbol("c".to_owned()))])
+    );
+}
+
+#[test]
+fn datum_comment_needs_a_datum() {
+    assert!(parse::parse_all("(a #;)").is_err());
+    assert!(parse::parse_all("#;").is_err());
+}
+
+
+
+// Spans
+
+#[test]
+fn spans_cover_every_node() {
+    use rexp::span::{Span, Spanned, SpannedQuote, SpannedSexp};
+    let sym = |name: &str, start, end| {
+        Spanned::new(
+            SpannedSexp::Constant(Atom::Symbol(name.to_owned())),
+            Span::new(start, end),
+        )
+    };
+    assert_eq!(
+        parse::spanned::parse_all("  (a 'b)\n#(c)"),
+        Ok(vec![
+            Spanned::new(
+                SpannedSexp::List(vec![
+                    sym("a", 3, 4),
+                    Spanned::new(
+                        SpannedSexp::Quote(SpannedQuote::Quote(Box::new(sym("b", 6, 7)))),
+                        Span::new(5, 7),
+                    ),
+                ]),
+                Span::new(2, 8),
+            ),
+            Spanned::new(SpannedSexp::Vector(vec![sym("c", 11, 12)]), Span::new(9, 13)),
+        ])
+    );
+}
+
+#[test]
+fn spanned_tree_strips_to_plain_tree() {
+    let src = "(define (f x) ; doc\n  `(,(g x) #(1 2.5 \"s\")))";
+    let spanned = parse::spanned::parse_all(src).unwrap();
+    assert_eq!(
+        spanned.into_iter().map(Sexp::from).collect::<Vec<_>>(),
+        parse::parse_all(src).unwrap()
+    );
+}
+
+#[test]
+fn spans_map_to_lines() {
+    use rexp::span::{LineCol, LineIndex};
+    let src = "(a)\n\n  (b\n   c)";
+    let index = LineIndex::new(src);
+    let forms = parse::spanned::parse_all(src).unwrap();
+    assert_eq!(index.line_col(forms[1].span.start), LineCol { line: 3, column: 3 });
+    assert_eq!(index.line_col(forms[1].span.end), LineCol { line: 4, column: 6 });
+}
+
+
+
+// Errors
+
+#[test]
+fn errors_report_kind_and_position() {
+    use rexp::{error::ErrorKind, Error};
+    let err: Error = parse::parse_all("(ok)\n(still (open)\n").unwrap_err();
+    assert_eq!(err.kind(), &ErrorKind::UnclosedParen);
+    assert_eq!((err.line(), err.column()), (2, 1));
+
+    let err = parse::spanned::parse_all("\n  ,42").unwrap_err();
+    assert_eq!(err.kind(), &ErrorKind::UnquoteLiteral);
+    assert_eq!(err.line(), 2);
+}
+
+#[test]
+fn error_display_points_at_the_problem() {
+    let rendered = parse::parse_all("(a b))").unwrap_err().to_string();
+    assert!(rendered.starts_with("error: unexpected input"));
+    assert!(rendered.contains("1 | (a b))\n  |      ^"));
+}
+
+#[test]
+fn atoms_cannot_run_together() {
+    use rexp::error::ErrorKind;
+    let err = parse::parse_all("(+ 1 123abc)").unwrap_err();
+    assert_eq!((err.kind(), err.column()), (&ErrorKind::MalformedNumber, 6));
+    let err = parse::parse_all("(define a#b 1)").unwrap_err();
+    assert_eq!((err.kind(), err.column()), (&ErrorKind::InvalidSymbol, 9));
+    // Strings, quotes and comments end the token before them
+    assert_eq!(
+        parse::parse_all("a\"b\"c'd;e"),
+        Ok(vec![
+            Sexp::Constant(Atom::Symbol("a".to_owned())),
+            Sexp::Constant(Atom::String("b".to_owned())),
+            Sexp::Constant(Atom::Symbol("c".to_owned())),
+            Sexp::Quote(Quote(Box::new(Sexp::Constant(Atom::Symbol("d".to_owned()))))),
+        ])
+    );
+}
+
+
+
+// Booleans
+
+#[test]
+fn boolean_constants() {
+    assert_eq!(
+        parse::parse_all("(#t #f #true #false)"),
+        Ok(vec![Sexp::List(vec![
+            Sexp::Constant(Atom::Bool(true)),
+            Sexp::Constant(Atom::Bool(false)),
+            Sexp::Constant(Atom::Bool(true)),
+            Sexp::Constant(Atom::Bool(false)),
+        ])])
+    );
+}
+
+
+
+// Dotted lists
+
+fn sym(name: &str) -> Sexp {
+    Sexp::Constant(Atom::Symbol(name.to_owned()))
+}
+
+#[test]
+fn dotted_pairs() {
+    assert_eq!(
+        parse::parse_all("(a . b) (a b . c) ((k . 1))"),
+        Ok(vec![
+            Sexp::DottedList(vec![sym("a")], Box::new(sym("b"))),
+            Sexp::DottedList(vec![sym("a"), sym("b")], Box::new(sym("c"))),
+            Sexp::List(vec![Sexp::DottedList(
+                vec![sym("k")],
+                Box::new(Sexp::Constant(Atom::Num(Num::Int(1)))),
+            )]),
+        ])
+    );
+}
+
+#[test]
+fn dotted_list_tails_are_spliced() {
+    assert_eq!(
+        parse::parse_all("(a . (b c)) (a . (b . c)) (a . ())"),
+        Ok(vec![
+            Sexp::List(vec![sym("a"), sym("b"), sym("c")]),
+            Sexp::DottedList(vec![sym("a"), sym("b")], Box::new(sym("c"))),
+            Sexp::List(vec![sym("a")]),
+        ])
+    );
+}
+
+#[test]
+fn dots_inside_symbols_are_fine() {
+    assert_eq!(
+        parse::parse_all("(a ... .b a.b)"),
+        Ok(vec![Sexp::List(vec![sym("a"), sym("..."), sym(".b"), sym("a.b")])])
+    );
+}
+
+#[test]
+fn misplaced_dots_fail() {
+    use rexp::error::ErrorKind;
+    for src in &["( . a)", "(a . b c)", "(a .)", "(a . . b)", ".", "'.", "#(a . b)"] {
+        assert_eq!(
+            parse::parse_all(src).unwrap_err().kind(),
+            &ErrorKind::MisplacedDot,
+            "{}",
+            src
+        );
+    }
+    // Running out of input is still an unclosed list
+    assert_eq!(
+        parse::parse_all("(a . b").unwrap_err().kind(),
+        &ErrorKind::UnclosedParen
+    );
+}
+
+#[test]
+fn spanned_dotted_pairs() {
+    use rexp::span::{Span, Spanned, SpannedSexp};
+    let parsed = parse::spanned::parse_all("(a . (b . c))").unwrap();
+    match &parsed[0].node {
+        SpannedSexp::DottedList(items, tail) => {
+            assert_eq!(items.len(), 2);
+            assert_eq!(items[1].span, Span::new(6, 7));
+            assert_eq!(
+                **tail,
+                Spanned::new(
+                    SpannedSexp::Constant(Atom::Symbol("c".to_owned())),
+                    Span::new(10, 11)
+                )
+            );
+        }
+        other => panic!("expected a dotted list, got {:?}", other),
+    }
+    assert!(parse::spanned::parse_all("(a . b c)").is_err());
+}
+
+
+
+// Unquoting
+
+#[test]
+fn unquote_and_splice() {
+    assert_eq!(
+        parse::parse_all("`(a ,b ,@cs ,@(f x))"),
+        Ok(vec![Sexp::Quote(Quasi(Box::new(Sexp::List(vec![
+            sym("a"),
+            Sexp::Quote(UnQuote(Box::new(sym("b")))),
+            Sexp::Quote(Splice(Box::new(sym("cs")))),
+            Sexp::Quote(Splice(Box::new(Sexp::List(vec![sym("f"), sym("x")])))),
+        ]))))])
+    );
+}
+
+#[test]
+fn at_sign_is_just_a_symbol_character() {
+    assert_eq!(
+        parse::parse_all("(@foo a@b @)"),
+        Ok(vec![Sexp::List(vec![sym("@foo"), sym("a@b"), sym("@")])])
+    );
+}
+
+#[test]
+fn spanned_unquote_and_splice() {
+    use rexp::span::{Span, SpannedQuote, SpannedSexp};
+    let parsed = parse::spanned::parse_all(",@xs ,y").unwrap();
+    assert!(matches!(parsed[0].node, SpannedSexp::Quote(SpannedQuote::Splice(_))));
+    assert_eq!(parsed[0].span, Span::new(0, 4));
+    assert!(matches!(parsed[1].node, SpannedSexp::Quote(SpannedQuote::UnQuote(_))));
+    assert!(parse::spanned::parse_all(",@1").is_err());
+}
+
+
+
+// Printing
+
+#[test]
+fn print_atoms() {
+    let print = |a: Atom| a.to_string();
+    assert_eq!(print(Atom::Num(Num::Int(-12))), "-12");
+    assert_eq!(print(Atom::Num(Num::Float(2.0))), "2.0");
+    assert_eq!(print(Atom::Num(Num::Float(-0.25))), "-0.25");
+    assert_eq!(print(Atom::Bool(true)), "#t");
+    assert_eq!(print(Atom::Char(' ')), "#\\space");
+    assert_eq!(print(Atom::Char('\n')), "#\\newline");
+    assert_eq!(print(Atom::Char('\u{1}')), "#\\x1");
+    assert_eq!(print(Atom::Char('λ')), "#\\λ");
+    assert_eq!(print(Atom::String("say \"hi\"\n\\".to_owned())), r#""say \"hi\"\n\\""#);
+    assert_eq!(print(Atom::Symbol("car".to_owned())), "car");
+    assert_eq!(print(Atom::Symbol("two words".to_owned())), "|two words|");
+    assert_eq!(print(Atom::Symbol("12".to_owned())), "|12|");
+    assert_eq!(print(Atom::Symbol("#t".to_owned())), "|#t|");
+    assert_eq!(print(Atom::Symbol("a b|c".to_owned())), r"|a b\|c|");
+    assert_eq!(print(Atom::Symbol("".to_owned())), "||");
+}
+
+#[test]
+fn print_compound() {
+    let src = "(define (f . args) `(,@args #(1 \"two\" #\\3) ,x 'y))";
+    assert_eq!(src.parse::<Sexp>().unwrap().to_string(), src);
+    assert_eq!(
+        Sexp::Quote(UnQuote(Box::new(sym("@x")))).to_string(),
+        ",|@x|"
+    );
+}
+
+#[test]
+fn from_str_wants_one_datum() {
+    assert_eq!(" ; hi\n (a) ".parse::<Sexp>(), Ok(Sexp::List(vec![sym("a")])));
+    assert!("".parse::<Sexp>().is_err());
+    assert!("a b".parse::<Sexp>().is_err());
+}
+
+
+
+// Big integers
+
+#[cfg(not(feature = "bigint"))]
+#[test]
+fn overflowing_integers_are_errors_not_symbols() {
+    use rexp::error::ErrorKind;
+    let err = parse::parse_all("(x 123456789012345678901234567890)").unwrap_err();
+    assert_eq!(err.kind(), &ErrorKind::IntegerOverflow);
+    assert_eq!(err.column(), 4);
+}
+
+#[cfg(feature = "bigint")]
+#[test]
+fn overflowing_integers_are_bigints() {
+    let big: num_bigint::BigInt = "123456789012345678901234567890".parse().unwrap();
+    let src = "(x 123456789012345678901234567890)";
+    let parsed = parse::parse_all(src).unwrap();
+    assert_eq!(
+        parsed,
+        vec![Sexp::List(vec![sym("x"), Sexp::Constant(Atom::Num(Num::BigInt(big)))])]
+    );
+    assert_eq!(parsed[0].to_string(), src);
+}
+
+
+
+// Limits
+
+use rexp::error::{ErrorKind, Limit};
+
+fn limit_hit(src: &str, options: &parse::ParseOptions) -> Option<(Limit, usize, usize)> {
+    match parse::parse_all_with(src, options) {
+        Err(e) => match e.kind() {
+            ErrorKind::LimitExceeded { limit, max } => Some((*limit, *max, e.offset())),
+            _ => None,
+        },
+        Ok(_) => None,
+    }
+}
+
+#[test]
+fn deep_nesting_is_an_error_not_a_crash() {
+    let src = "(".repeat(100_000) + &")".repeat(100_000);
+    let options = parse::ParseOptions::default();
+    assert_eq!(limit_hit(&src, &options), Some((Limit::Depth, 128, 128)));
+    let src = "'".repeat(100_000) + "x";
+    assert_eq!(limit_hit(&src, &options), Some((Limit::Depth, 128, 128)));
+    // Right at the limit is fine
+    let src = "(".repeat(128) + &")".repeat(128);
+    assert!(parse::parse_with(&src, &options).is_ok());
+}
+
+#[test]
+fn size_limits() {
+    let options = parse::ParseOptions {
+        max_atom_len: 5,
+        max_list_len: 3,
+        max_total_nodes: 7,
+        ..parse::ParseOptions::default()
+    };
+    assert_eq!(limit_hit("(abcde \"abc\")", &options), None);
+    assert_eq!(limit_hit("(a abcdef)", &options), Some((Limit::AtomLength, 5, 3)));
+    assert_eq!(limit_hit("(a \"abcd\")", &options), Some((Limit::AtomLength, 5, 3)));
+    assert_eq!(limit_hit("abcdef", &options), Some((Limit::AtomLength, 5, 0)));
+    assert_eq!(limit_hit("#(1 2 3 4)", &options), Some((Limit::ListLength, 3, 9)));
+    assert_eq!(limit_hit("(1 2 3) (4 5 6)", &options), Some((Limit::TotalNodes, 7, 15)));
+    // Errors from the parser itself come through as before
+    assert_eq!(
+        parse::parse_with("(a", &options).unwrap_err().kind(),
+        &ErrorKind::UnclosedParen
+    );
+}
+
+fn depth_error(e: &rexp::Error) -> bool {
+    e.kind() == &ErrorKind::LimitExceeded { limit: Limit::Depth, max: 128 }
+}
+
+#[test]
+fn every_entry_point_limits_depth() {
+    use rexp::{parse::incremental::{Expected, Incremental, Status}, pretty, Reader};
+    let src = "(".repeat(200_000) + &")".repeat(200_000);
+    assert!(depth_error(&parse::parse(&src).unwrap_err()));
+    assert!(depth_error(&parse::parse_all(&src).unwrap_err()));
+    assert!(depth_error(&src.parse::<Sexp>().unwrap_err()));
+    assert!(depth_error(&parse::spanned::parse_all(&src).unwrap_err()));
+    assert!(depth_error(&parse::borrowed::parse(&src).unwrap_err()));
+    assert!(depth_error(&parse::borrowed::parse_all(&src).unwrap_err()));
+    let mut table = rexp::intern::SymbolTable::new();
+    assert!(depth_error(&parse::interned::parse_all(&src, &mut table).unwrap_err()));
+    assert!(depth_error(&parse::cst::parse(&src).unwrap_err()));
+    assert!(depth_error(&pretty::format(&src, &pretty::Options::default()).unwrap_err()));
+
+    let err = Incremental::new().feed(&src).unwrap_err();
+    assert!(depth_error(&err));
+    assert_eq!(err.offset(), 128);
+
+    let mut reader = Reader::new(src.as_bytes());
+    let err = reader.next().unwrap().unwrap_err();
+    assert!(depth_error(&err));
+    assert_eq!(err.offset(), 128);
+    assert!(reader.next().is_none());
+
+    // The limits don't look inside an unclosed `|`, so nothing may read past it
+    let src = "|a ".to_owned() + &"(".repeat(200_000);
+    let unclosed = |e: rexp::Error| e.kind() == &ErrorKind::UnterminatedSymbol && e.offset() == 0;
+    assert!(unclosed(parse::parse(&src).unwrap_err()));
+    assert!(unclosed(parse::parse_all(&src).unwrap_err()));
+    assert!(unclosed(src.parse::<Sexp>().unwrap_err()));
+    assert!(unclosed(parse::spanned::parse_all(&src).unwrap_err()));
+    assert!(unclosed(parse::borrowed::parse(&src).unwrap_err()));
+    assert!(unclosed(parse::borrowed::parse_all(&src).unwrap_err()));
+    assert!(unclosed(parse::interned::parse_all(&src, &mut table).unwrap_err()));
+    assert!(unclosed(parse::cst::parse(&src).unwrap_err()));
+    assert!(unclosed(pretty::format(&src, &pretty::Options::default()).unwrap_err()));
+    assert_eq!(
+        Incremental::new().feed(&src),
+        Ok(Status::Incomplete { depth: 0, expected: Expected::Bar })
+    );
+    assert!(unclosed(Reader::new(src.as_bytes()).next().unwrap().unwrap_err()));
+}
+
+#[test]
+fn entry_points_agree_on_an_unclosed_bar() {
+    use rexp::{parse::incremental::{Expected, Incremental, Status}, Reader};
+    let src = "|a (b) c";
+    assert_eq!(parse::parse_all(src).unwrap_err().kind(), &ErrorKind::UnterminatedSymbol);
+    let mut reader = Reader::new(src.as_bytes());
+    assert_eq!(reader.next().unwrap().unwrap_err().kind(), &ErrorKind::UnterminatedSymbol);
+    let mut parser = Incremental::new();
+    assert_eq!(parser.feed(src), Ok(Status::Incomplete { depth: 0, expected: Expected::Bar }));
+    // Closing it makes one symbol of the lot
+    assert_eq!(parser.feed("|"), Ok(Status::Complete(vec![Sexp::Constant(Atom::Symbol("a (b) c".to_owned()))])));
+}
+
+#[test]
+fn readers_take_options() {
+    use rexp::{parse::incremental::{Incremental, Status}, Reader};
+    let options = parse::ParseOptions { max_list_len: 2, ..parse::ParseOptions::default() };
+
+    let mut reader = Reader::with_options(&b"(a b)\n(c d e)"[..], options);
+    assert!(reader.next().unwrap().is_ok());
+    let err = reader.next().unwrap().unwrap_err();
+    assert_eq!(err.kind(), &ErrorKind::LimitExceeded { limit: Limit::ListLength, max: 2 });
+    assert_eq!((err.line(), err.column()), (2, 7));
+    assert!(reader.next().is_none());
+
+    let mut parser = Incremental::with_options(options);
+    assert!(parser.feed("(a b) (c\n").is_ok());
+    let err = parser.feed("d e)\n").unwrap_err();
+    assert_eq!(err.kind(), &ErrorKind::LimitExceeded { limit: Limit::ListLength, max: 2 });
+    // The options outlast the error
+    assert!(parser.feed("(a b c)\n").is_err());
+    assert_eq!(parser.feed("(a b)\n"), Ok(Status::Complete(vec!["(a b)".parse().unwrap()])));
+}
@@ -0,0 +1,76 @@
+use proptest::prelude::*;
+
+use rexp::expr::{Atom, Num, Quote, Ratio, Sexp};
+
+fn atom() -> impl Strategy<Value = Atom> {
+    prop_oneof![
+        any::<i64>().prop_map(|i| Atom::Num(Num::Int(i))),
+        any::<f64>()
+            // NaN never equals itself
+            .prop_filter("not NaN", |x| !x.is_nan())
+            .prop_map(|x| Atom::Num(Num::Float(x))),
+        (any::<i64>(), 2..i64::MAX)
+            .prop_filter_map("whole", |(n, d)| Ratio::new(n, d).filter(|r| r.denom() != 1))
+            .prop_map(|r| Atom::Num(Num::Ratio(r))),
+        any::<bool>().prop_map(Atom::Bool),
+        any::<char>().prop_map(Atom::Char),
+        any::<String>().prop_map(Atom::String),
+        any::<String>().prop_map(Atom::Symbol),
+        "[a-z!$%&*/:<=>?^_~+-][a-z0-9!$%&*/:<=>?^_~+.@-]{0,8}".prop_map(Atom::Symbol),
+    ]
+}
+
+fn symbol() -> impl Strategy<Value = Sexp> {
+    any::<String>().prop_map(|s| Sexp::Constant(Atom::Symbol(s)))
+}
+
+fn sexp() -> impl Strategy<Value = Sexp> {
+    atom().prop_map(Sexp::Constant).prop_recursive(4, 64, 8, |inner| {
+        // Only symbols and compound datums can be unquoted
+        let unquotable = prop_oneof![
+            symbol(),
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Sexp::List),
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Sexp::Vector),
+        ];
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(Sexp::List),
+            prop::collection::vec(inner.clone(), 0..8).prop_map(Sexp::Vector),
+            // Dotted tails are never lists themselves
+            (
+                prop::collection::vec(inner.clone(), 1..4),
+                atom().prop_map(Sexp::Constant),
+            )
+                .prop_map(|(items, tail)| Sexp::DottedList(items, Box::new(tail))),
+            inner.clone().prop_map(|s| Sexp::Quote(Quote::Quote(Box::new(s)))),
+            inner.prop_map(|s| Sexp::Quote(Quote::Quasi(Box::new(s)))),
+            unquotable.clone().prop_map(|s| Sexp::Quote(Quote::UnQuote(Box::new(s)))),
+            unquotable.prop_map(|s| Sexp::Quote(Quote::Splice(Box::new(s)))),
+        ]
+    })
+}
+
+proptest! {
+    #[test]
+    fn print_then_parse_is_identity(x in sexp()) {
+        let printed = x.to_string();
+        prop_assert_eq!(printed.parse::<Sexp>(), Ok(x), "printed as {}", printed);
+    }
+
+    #[test]
+    fn borrowed_parse_agrees_with_owned(x in sexp()) {
+        use rexp::{borrowed, parse};
+        let printed = x.to_string();
+        let owned = parse::borrowed::parse(&printed).map(borrowed::Sexp::into_owned);
+        prop_assert_eq!(owned, parse::parse(&printed), "printed as {}", printed);
+    }
+
+    #[test]
+    fn cst_is_lossless(x in sexp()) {
+        use rexp::parse::cst;
+        // Pad with trivia so there's some to keep
+        let printed = format!(" ;c\n{} #| b |# ", x);
+        let root = cst::parse(&printed).unwrap();
+        prop_assert_eq!(root.to_string(), printed.clone());
+        prop_assert_eq!(root.to_sexps(), Ok(vec![x]), "printed as {}", printed);
+    }
+}
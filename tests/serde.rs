@@ -0,0 +1,179 @@
+#![cfg(feature = "serde")]
+
+use std::collections::BTreeMap;
+
+use rexp::{expr::Sexp, span::LineCol};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Config {
+    name: String,
+    port: u16,
+    tags: Vec<String>,
+    owner: Option<String>,
+    mode: Mode,
+    limits: Limits,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Mode {
+    Fast,
+    Retry(u32),
+    Between(i64, i64),
+    Backoff { base: f64, max: Option<u32> },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Limits(u32, u32);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Marker;
+
+fn config() -> Config {
+    Config {
+        name: "rexp".to_owned(),
+        port: 8080,
+        tags: vec!["a".to_owned(), "b".to_owned()],
+        owner: None,
+        mode: Mode::Fast,
+        limits: Limits(1, 2),
+    }
+}
+
+fn position(e: &rexp::de::Error) -> Option<(usize, usize)> {
+    e.position().map(|LineCol { line, column }| (line, column))
+}
+
+#[test]
+fn structs_are_keyword_lists() {
+    assert_eq!(
+        rexp::to_string(&config()),
+        Ok("(:name \"rexp\" :port 8080 :tags (\"a\" \"b\") :owner nil :mode fast :limits (1 2))".to_owned())
+    );
+}
+
+#[test]
+fn enums_are_tagged_forms() {
+    let modes = vec![
+        Mode::Fast,
+        Mode::Retry(3),
+        Mode::Between(-1, 1),
+        Mode::Backoff { base: 1.5, max: Some(10) },
+    ];
+    let text = rexp::to_string(&modes).unwrap();
+    assert_eq!(text, "(fast (retry 3) (between -1 1) (backoff :base 1.5 :max 10))");
+    assert_eq!(rexp::from_str::<Vec<Mode>>(&text), Ok(modes));
+    // A variant with nothing after it can also be a list of one
+    assert_eq!(rexp::from_str::<Mode>("(fast)"), Ok(Mode::Fast));
+}
+
+#[test]
+fn round_trips() {
+    let config = Config { owner: Some("me".to_owned()), ..config() };
+    assert_eq!(rexp::from_str(&rexp::to_string(&config).unwrap()), Ok(config));
+    assert_eq!(rexp::from_str(&rexp::to_string_pretty(&Marker).unwrap()), Ok(Marker));
+
+    let mut map = BTreeMap::new();
+    map.insert("x".to_owned(), ('a', true));
+    map.insert("y".to_owned(), ('b', false));
+    let text = rexp::to_string(&map).unwrap();
+    assert_eq!(text, "((\"x\" (#\\a #t)) (\"y\" (#\\b #f)))");
+    assert_eq!(rexp::from_str(&text), Ok(map));
+
+    let sexp = rexp::to_sexp(&Mode::Retry(3)).unwrap();
+    assert_eq!(sexp, "(retry 3)".parse::<Sexp>().unwrap());
+    assert_eq!(rexp::from_sexp(&sexp), Ok(Mode::Retry(3)));
+}
+
+#[test]
+fn hand_written_input() {
+    let src = r#"
+        ; the usual
+        (:port 8080
+         :name "rexp"
+         :limits #(1 2)
+         :tags (a b)
+         :mode (backoff :base 2 :max nil)
+         :owner "me")
+    "#;
+    let config: Config = rexp::from_str(src).unwrap();
+    assert_eq!(config.owner, Some("me".to_owned()));
+    // Symbols read as strings, and integers as floats
+    assert_eq!(config.tags, vec!["a".to_owned(), "b".to_owned()]);
+    assert_eq!(config.mode, Mode::Backoff { base: 2.0, max: None });
+}
+
+#[test]
+fn errors_point_at_the_field() {
+    let src = "(:name \"rexp\"\n :port \"eighty\"\n :tags () :owner nil :mode fast :limits (1 2))";
+    let err = rexp::from_str::<Config>(src).unwrap_err();
+    assert_eq!(position(&err), Some((2, 8)));
+    assert!(err.message().contains("invalid type: string \"eighty\""), "{}", err);
+    assert!(err.to_string().ends_with("at line 2, column 8"));
+
+    let err = rexp::from_str::<Config>("(:name \"rexp\"\n :port 80000)").unwrap_err();
+    assert_eq!(position(&err), Some((2, 8)));
+
+    let err = rexp::from_str::<Config>("  (:name \"rexp\")").unwrap_err();
+    assert_eq!(err.message(), "missing field `port`");
+    assert_eq!(position(&err), Some((1, 3)));
+
+    let err = rexp::from_str::<Vec<Mode>>("(fast\n (slow 1))").unwrap_err();
+    assert!(err.message().starts_with("unknown variant `slow`"), "{}", err);
+    assert_eq!(position(&err), Some((2, 3)));
+
+    let err = rexp::from_str::<Config>("(name \"rexp\")").unwrap_err();
+    assert_eq!(err.message(), "expected a keyword like `:name`");
+    assert_eq!(position(&err), Some((1, 2)));
+
+    let err = rexp::from_str::<Limits>("(1 2 3)").unwrap_err();
+    assert_eq!(err.message(), "too many items");
+    assert_eq!(position(&err), Some((1, 6)));
+}
+
+#[test]
+fn source_errors() {
+    let err = rexp::from_str::<Limits>("(1 2").unwrap_err();
+    assert_eq!(position(&err), Some((1, 1)));
+    let err = rexp::from_str::<Limits>("(1 2) (3 4)").unwrap_err();
+    assert_eq!(err.message(), "expected the end of input");
+    assert_eq!(position(&err), Some((1, 7)));
+    assert!(rexp::from_str::<Limits>("; nothing").is_err());
+}
+
+#[test]
+fn sexp_errors_have_no_position() {
+    let sexp: Sexp = "(1 x)".parse().unwrap();
+    let err = rexp::from_sexp::<Limits>(&sexp).unwrap_err();
+    assert_eq!((err.span(), err.position()), (None, None));
+}
+
+#[test]
+fn values_without_a_form() {
+    #[cfg(not(feature = "bigint"))]
+    assert!(rexp::to_string(&u64::MAX).is_err());
+    // With `bigint` there's a form for every `u64`
+    #[cfg(feature = "bigint")]
+    assert_eq!(rexp::to_string(&u64::MAX), Ok(u64::MAX.to_string()));
+    assert_eq!(rexp::to_string(&(i64::MAX as u64)), Ok(i64::MAX.to_string()));
+}
+
+#[cfg(feature = "bigint")]
+#[test]
+fn big_unsigned_integers_read_back() {
+    use rexp::expr::{Atom, Num};
+    let sexp = rexp::to_sexp(&u64::MAX).unwrap();
+    assert!(matches!(sexp, Sexp::Constant(Atom::Num(Num::BigInt(_)))));
+    assert_eq!(rexp::from_str::<u64>(&u64::MAX.to_string()), Ok(u64::MAX));
+    assert_eq!(rexp::from_sexp::<u64>(&sexp), Ok(u64::MAX));
+    assert!(rexp::from_str::<i64>(&u64::MAX.to_string()).is_err());
+    assert!(rexp::from_str::<u64>("18446744073709551616").is_err());
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    let src = "(".repeat(200_000) + &")".repeat(200_000);
+    let err = rexp::from_str::<Vec<Marker>>(&src).unwrap_err();
+    assert!(err.message().contains("nesting depth is over the limit of 128"), "{}", err);
+}